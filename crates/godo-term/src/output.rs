@@ -63,6 +63,8 @@ pub type Result<T> = StdResult<T, OutputError>;
 ///
 /// The spinner will automatically stop and clear when dropped.
 pub trait Spinner: Send {
+    /// Replace the message shown next to the spinner.
+    fn set_message(&self, msg: &str);
     /// Stop the spinner and display a success message.
     fn finish_success(self: Box<Self>, msg: &str);
     /// Stop the spinner and display a failure message.
@@ -110,6 +112,7 @@ pub trait Output: Send + Sync {
 struct QuietSpinner;
 
 impl Spinner for QuietSpinner {
+    fn set_message(&self, _msg: &str) {}
     fn finish_success(self: Box<Self>, _msg: &str) {}
     fn finish_fail(self: Box<Self>, _msg: &str) {}
     fn finish_clear(self: Box<Self>) {}
@@ -186,6 +189,10 @@ struct TerminalSpinner {
 }
 
 impl Spinner for TerminalSpinner {
+    fn set_message(&self, msg: &str) {
+        self.bar.set_message(msg.to_string());
+    }
+
     fn finish_success(self: Box<Self>, msg: &str) {
         self.bar
             .set_style(ProgressStyle::with_template(&format!("\x1b[32m✓\x1b[0m {msg}")).unwrap());
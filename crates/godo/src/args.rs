@@ -1,5 +1,7 @@
 use clap::{ArgGroup, Parser, Subcommand};
 
+use crate::config::{AfterRun, UncommittedSetting};
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 #[command(group(
@@ -43,31 +45,99 @@ pub enum Commands {
     /// Run a command in an isolated workspace
     Run {
         /// Keep the sandbox after the command exits
-        #[arg(long)]
+        #[arg(long, conflicts_with = "after")]
         keep: bool,
 
+        /// What to do with the sandbox after the command exits
+        #[arg(long, value_enum, value_name = "ACTION")]
+        after: Option<AfterRun>,
+
+        /// How to handle uncommitted changes in the repository
+        #[arg(long, value_enum, value_name = "POLICY")]
+        uncommitted: Option<UncommittedSetting>,
+
+        /// Skip the configured setup hooks when creating the sandbox
+        #[arg(long = "no-hooks")]
+        no_hooks: bool,
+
+        /// Run the setup hooks from the project's .godo.toml without asking for trust
+        #[arg(long = "trust-hooks", conflicts_with = "no_hooks")]
+        trust_hooks: bool,
+
+        /// Create the sandbox from a commit, tag or branch instead of the working tree
+        #[arg(long = "from", value_name = "REV")]
+        from: Option<String>,
+
+        /// Create a throwaway sandbox on a detached HEAD, without a branch (cannot be committed)
+        #[arg(long, conflicts_with = "commit")]
+        detached: bool,
+
+        /// Refuse to create the sandbox (or ask first) if files would be fully copied
+        #[arg(long = "require-cow")]
+        require_cow: bool,
+
         /// Automatically commit all changes with the specified message after command exits
         #[arg(long)]
         commit: Option<String>,
 
+        /// With --commit, commit only changes made inside the sandbox, leaving out uncommitted
+        /// changes that were included when it was created
+        #[arg(long, requires = "commit")]
+        delta: bool,
+
         /// Force shell evaluation with $SHELL -c
         #[arg(long = "sh")]
         sh: bool,
 
-        /// Exclude directories that match glob (can be specified multiple times)
+        /// Exclude paths matching a gitignore-style glob (can be specified multiple times)
         #[arg(long = "exclude", value_name = "GLOB")]
         excludes: Vec<String>,
 
-        /// Name of the sandbox
-        name: String,
+        /// Restrict the sandbox to a directory with a sparse checkout (can be specified multiple
+        /// times)
+        #[arg(long = "only", value_name = "PATH")]
+        only: Vec<String>,
+
+        /// Generate a unique sandbox name instead of taking one; all arguments form the command
+        #[arg(long = "auto-name")]
+        auto_name: bool,
+
+        /// Name of the sandbox, or `-` to generate a unique one
+        #[arg(required_unless_present = "auto_name")]
+        name: Option<String>,
 
         /// Command to execute (if omitted, opens interactive shell)
         command: Vec<String>,
     },
 
+    /// Create a new sandbox from an existing sandbox's branch and working tree
+    Fork {
+        /// Name of the sandbox to fork
+        source: String,
+
+        /// Name of the new sandbox
+        name: String,
+    },
+
     /// Show existing sandboxes
     #[command(alias = "ls")]
-    List,
+    List {
+        /// Only show sandboxes whose names start with this prefix, such as `bug/`
+        prefix: Option<String>,
+
+        /// Show sandboxes of every project in the godo directory, flagging projects whose
+        /// repository no longer exists
+        #[arg(long)]
+        all: bool,
+
+        /// Delete the sandboxes of projects whose repository no longer exists
+        #[arg(long, requires = "all")]
+        prune: bool,
+
+        /// Measure the disk usage of each sandbox, which walks every sandbox tree
+        #[arg(long)]
+        size: bool,
+    },
 
     /// Diff a sandbox against its recorded base commit
     Diff {
@@ -75,9 +145,14 @@ pub enum Commands {
         name: Option<String>,
 
         /// Override the base commit used for diffing
-        #[arg(long, value_name = "COMMIT")]
+        #[arg(long, value_name = "COMMIT", conflicts_with = "delta")]
         base: Option<String>,
 
+        /// Show only changes made inside the sandbox, leaving out uncommitted changes that were
+        /// included when it was created
+        #[arg(long)]
+        delta: bool,
+
         /// Override the pager command for diff output
         #[arg(long, value_name = "CMD", conflicts_with = "no_pager")]
         pager: Option<String>,
@@ -103,20 +178,41 @@ pub enum Commands {
         /// Name of the sandbox to clean (if not specified, cleans all sandboxes)
         name: Option<String>,
     },
+
+    /// Reconnect sandboxes after the repository or the godo directory was moved
+    Repair,
 }
 
 /// Parameters for the `godo run` command.
 pub struct RunRequest {
-    /// Keep the sandbox after command completion.
-    pub keep: bool,
+    /// Action to take on the sandbox after command completion.
+    pub after: AfterRun,
+    /// How to handle uncommitted changes in the source repository.
+    pub uncommitted: UncommittedSetting,
     /// Optional commit message for automatic commit.
     pub commit: Option<String>,
+    /// Commit only the changes made since the sandbox's baseline.
+    pub commit_delta: bool,
     /// Force shell execution.
     pub force_shell: bool,
-    /// Directory exclusions to apply when cloning.
+    /// Gitignore-style exclude patterns to apply when cloning.
     pub excludes: Vec<String>,
-    /// Name of the sandbox to operate on.
-    pub sandbox_name: String,
+    /// Revision to create the sandbox from instead of the working tree.
+    pub base: Option<String>,
+    /// Require copy-on-write cloning for a new sandbox.
+    pub require_cow: bool,
+    /// Create a new sandbox without a branch.
+    pub detached: bool,
+    /// Directories to restrict a new sandbox to; empty for a full sandbox.
+    pub sparse_paths: Vec<String>,
+    /// Setup hooks from the user config to run when the sandbox is created.
+    pub setup_hooks: Vec<String>,
+    /// Setup hooks from the project config, which run after the user's once trusted.
+    pub project_hooks: Vec<String>,
+    /// Run the project's setup hooks without checking that they are trusted.
+    pub trust_hooks: bool,
+    /// Name of the sandbox to operate on, or `None` to generate a unique one.
+    pub sandbox_name: Option<String>,
     /// Command to execute inside the sandbox.
     pub command: Vec<String>,
 }
@@ -6,12 +6,23 @@ use std::path::Path;
 
 use crate::ui::emit;
 
+/// What to diff a sandbox against.
+#[derive(Clone, Copy)]
+pub enum DiffBase<'a> {
+    /// The base commit recorded when the sandbox was created.
+    Recorded,
+    /// A commit given on the command line.
+    Override(&'a str),
+    /// The sandbox's baseline, showing only changes made inside the sandbox.
+    Baseline,
+}
+
 /// Run the `godo diff` command logic.
 pub fn diff(
     godo: &Godo,
     output: &dyn Output,
     name: Option<&str>,
-    base: Option<&str>,
+    base: DiffBase<'_>,
     pager: Option<String>,
     no_pager: bool,
     current_sandbox: Option<&str>,
@@ -27,7 +38,11 @@ pub fn diff(
         }
     };
 
-    let plan = godo.diff_plan(effective_name, base)?;
+    let plan = match base {
+        DiffBase::Recorded => godo.diff_plan(effective_name, None)?,
+        DiffBase::Override(base) => godo.diff_plan(effective_name, Some(base))?,
+        DiffBase::Baseline => godo.delta_diff_plan(effective_name)?,
+    };
 
     if plan.used_fallback {
         if let Some(target) = &plan.fallback_target {
@@ -46,7 +61,12 @@ pub fn diff(
 /// Execute a diff plan with the provided pager options.
 fn run_diff_plan(plan: &DiffPlan, pager: Option<String>, no_pager: bool) -> Result<()> {
     let pager = DiffPager::new(pager, no_pager);
-    let tracked_args = vec!["diff".to_string(), plan.base_commit.clone()];
+    let mut tracked_args = vec![
+        "diff".to_string(),
+        "--submodule=diff".to_string(),
+        plan.base_commit.clone(),
+    ];
+    tracked_args.extend(plan.target_tree.clone());
     run_git_diff_command(&plan.sandbox_path, &pager, &tracked_args)?;
 
     for path in &plan.untracked_files {
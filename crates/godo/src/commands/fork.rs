@@ -0,0 +1,31 @@
+use anyhow::Result;
+use godo_term::Output;
+use libgodo::{Godo, PrepareEvent};
+
+use crate::ui::{clone_progress_message, emit, warn_copied_files};
+
+/// Run the `godo fork` command logic.
+pub fn fork(godo: &Godo, output: &dyn Output, source: &str, name: &str) -> Result<()> {
+    let sandbox_path = godo.sandbox_path(name)?;
+    emit(output.message(&format!(
+        "Forking sandbox {source} into {name} with branch {} at {sandbox_path:?}",
+        godo.branch_name(name)
+    )))?;
+
+    let spinner = output.spinner("Cloning sandbox...");
+    let result = godo.fork_sandbox(source, name, &mut |event| {
+        if let PrepareEvent::Clone(progress) = event {
+            spinner.set_message(&clone_progress_message(&progress));
+        }
+    });
+    match result {
+        Ok(stats) => {
+            spinner.finish_success("Sandbox ready");
+            warn_copied_files(output, &stats)
+        }
+        Err(err) => {
+            spinner.finish_fail("Fork failed");
+            Err(err.into())
+        }
+    }
+}
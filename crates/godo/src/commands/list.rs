@@ -1,28 +1,151 @@
+use std::path::Path;
+
 use anyhow::Result;
 use godo_term::Output;
-use libgodo::{Godo, MergeStatus, SandboxListEntry};
+use libgodo::{DiskUsage, Godo, MergeStatus, ProjectInfo, SandboxListEntry};
 
-use crate::ui::emit;
+use crate::{
+    config::Config,
+    ui::{emit, format_bytes, prompt_confirm},
+};
 
-/// Run the `godo list` command logic.
-pub fn list(godo: &Godo, output: &dyn Output) -> Result<()> {
-    let entries = godo.list()?;
+/// Run the `godo list` command logic, showing only names starting with `prefix` if given.
+///
+/// Disk usage is only measured and shown when `size` is set.
+pub fn list(godo: &Godo, output: &dyn Output, prefix: Option<&str>, size: bool) -> Result<()> {
+    let entries = godo.list_matching(prefix.unwrap_or_default(), size)?;
     if entries.is_empty() {
-        emit(output.message("No sandboxes found."))?;
+        let message = match prefix {
+            Some(prefix) => format!("No sandboxes matching {prefix} found."),
+            None => "No sandboxes found.".to_string(),
+        };
+        emit(output.message(&message))?;
+        return Ok(());
+    }
+    render_entries(output, entries, size)
+}
+
+/// Run the `godo list --all` command logic across every project in the godo directory.
+///
+/// Projects whose repository no longer exists are flagged, and deleted when `prune` is set.
+pub fn list_all(
+    godo: &Godo,
+    output: &dyn Output,
+    godo_dir: &Path,
+    prefix: Option<&str>,
+    size: bool,
+    prune: bool,
+    no_prompt: bool,
+) -> Result<()> {
+    let projects = godo.projects()?;
+    if projects.is_empty() {
+        emit(output.message("No projects found."))?;
+        return Ok(());
+    }
+
+    for project in projects {
+        let section = output.section(&format!("{} ({})", project.id, project.repo_dir.display()));
+        if project.is_stale {
+            render_stale_project(godo, section.as_ref(), &project, prune, no_prompt)?;
+            continue;
+        }
+
+        // Each project may configure its own branch prefix. A broken config only affects its
+        // own project.
+        let config = match Config::load(godo_dir, &project.repo_dir) {
+            Ok(config) => config,
+            Err(err) => {
+                emit(section.fail(&format!("invalid configuration: {err:#}")))?;
+                continue;
+            }
+        };
+        let project_godo = godo.for_project(&project)?;
+        let project_godo = match &config.branch_prefix {
+            Some(prefix) => project_godo.with_branch_prefix(prefix)?,
+            None => project_godo,
+        };
+        let entries = project_godo.list_matching(prefix.unwrap_or_default(), size)?;
+        if entries.is_empty() {
+            emit(section.message("No sandboxes."))?;
+        } else {
+            render_entries(section.as_ref(), entries, size)?;
+        }
+    }
+    Ok(())
+}
+
+/// Flag a project whose repository is gone, deleting it when `prune` is set.
+fn render_stale_project(
+    godo: &Godo,
+    output: &dyn Output,
+    project: &ProjectInfo,
+    prune: bool,
+    no_prompt: bool,
+) -> Result<()> {
+    emit(output.fail("repository no longer exists"))?;
+    if !prune {
+        emit(output.message("remove with `godo list --all --prune`"))?;
         return Ok(());
     }
+    let prompt = format!(
+        "Delete {} and every sandbox in it?",
+        project.project_dir.display()
+    );
+    if !no_prompt && !prompt_confirm(output, &prompt)? {
+        emit(output.message("kept"))?;
+        return Ok(());
+    }
+    godo.prune_project(project)?;
+    emit(output.success("pruned"))
+}
 
+/// Render sandbox entries, followed by a line totalling their count and disk usage when `size`
+/// is set.
+fn render_entries(output: &dyn Output, entries: Vec<SandboxListEntry>, size: bool) -> Result<()> {
+    let count = entries.len();
+    let usages: Vec<DiskUsage> = entries
+        .iter()
+        .filter_map(|entry| entry.disk_usage)
+        .collect();
     for entry in entries {
         render_sandbox_entry(output, entry)?;
     }
+    if !size {
+        return Ok(());
+    }
+
+    let noun = if count == 1 { "sandbox" } else { "sandboxes" };
+    let total = DiskUsage {
+        apparent_bytes: usages.iter().map(|usage| usage.apparent_bytes).sum(),
+        exclusive_bytes: usages.iter().map(|usage| usage.exclusive_bytes).sum(),
+        shared_bytes: usages.iter().map(|usage| usage.shared_bytes).sum(),
+    };
+    emit(output.item(
+        "total",
+        &format!("{count} {noun}, {}", format_usage(&total)),
+    ))?;
 
     Ok(())
 }
 
+/// Format disk usage as the apparent size, followed by the exclusive/shared split when known.
+fn format_usage(usage: &DiskUsage) -> String {
+    let apparent = format_bytes(usage.apparent_bytes);
+    match (usage.exclusive_bytes, usage.shared_bytes) {
+        (Some(exclusive), Some(shared)) => format!(
+            "{apparent} ({} exclusive, {} shared)",
+            format_bytes(exclusive),
+            format_bytes(shared)
+        ),
+        _ => apparent,
+    }
+}
+
 /// Render a sandbox entry in list output.
 fn render_sandbox_entry(output: &dyn Output, entry: SandboxListEntry) -> Result<()> {
     let status = entry.status;
     let connections = entry.active_connections;
+    let disk_usage = entry.disk_usage;
 
     let has_unmerged = status.has_branch && matches!(status.merge_status, MergeStatus::Diverged);
     let has_uncommitted = status.has_worktree && status.has_uncommitted_changes;
@@ -34,7 +157,11 @@ fn render_sandbox_entry(output: &dyn Output, entry: SandboxListEntry) -> Result<
     } else if status.worktree_detached {
         emit(section.item("branch", "(detached HEAD)"))?;
     } else if status.has_branch {
-        emit(section.item("branch", &format!("godo/{}", status.name)))?;
+        emit(section.item("branch", &status.branch))?;
+    }
+
+    if let Some(usage) = &disk_usage {
+        emit(section.item("disk", &format_usage(usage)))?;
     }
 
     if connections > 0 {
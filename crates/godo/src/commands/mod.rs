@@ -1,5 +1,14 @@
+/// `godo clean`: remove unmodified worktrees and merged branches.
 pub mod clean;
+/// `godo diff`: show a sandbox's changes against its base.
 pub mod diff;
+/// `godo fork`: create a sandbox from an existing sandbox.
+pub mod fork;
+/// `godo list`: show sandboxes and their state.
 pub mod list;
+/// `godo remove`: delete a sandbox and its branch.
 pub mod remove;
+/// `godo repair`: reconnect sandboxes after a repository or godo directory move.
+pub mod repair;
+/// `godo run`: run a command in a sandbox.
 pub mod run;
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use godo_term::Output;
+use libgodo::{Godo, RepairReport};
+
+use crate::ui::emit;
+
+/// Run the `godo repair` command logic.
+pub fn repair(godo: &Godo, output: &dyn Output) -> Result<()> {
+    let spinner = output.spinner("Repairing sandboxes...");
+    let report = match godo.repair() {
+        Ok(report) => {
+            spinner.finish_clear();
+            report
+        }
+        Err(err) => {
+            spinner.finish_fail("Repair failed");
+            return Err(err.into());
+        }
+    };
+    render_report(output, &report)
+}
+
+/// Repair the project before a command that changes sandboxes, if it was moved.
+pub fn repair_if_needed(godo: &Godo, output: &dyn Output) -> Result<()> {
+    if !godo.needs_repair()? {
+        return Ok(());
+    }
+    emit(output.message("The repository or godo directory moved; reconnecting sandboxes."))?;
+    repair(godo, output)
+}
+
+/// Point at `godo repair` when the project was moved, without changing anything.
+pub fn hint_if_needed(godo: &Godo, output: &dyn Output) -> Result<()> {
+    if godo.needs_repair()? {
+        emit(output.warn(
+            "The repository or godo directory moved and some sandboxes are not connected; \
+             run `godo repair` to reconnect them.",
+        ))?;
+    }
+    Ok(())
+}
+
+/// Render what a repair moved and reconnected.
+fn render_report(output: &dyn Output, report: &RepairReport) -> Result<()> {
+    for project in &report.previous_projects {
+        emit(output.message(&format!(
+            "Moved sandboxes over from project directory {project}"
+        )))?;
+    }
+    if report.repaired.is_empty() {
+        emit(output.message("No sandboxes to repair."))?;
+        return Ok(());
+    }
+    for name in &report.repaired {
+        let label = if report.adopted.contains(name) {
+            "moved and repaired"
+        } else {
+            "repaired"
+        };
+        emit(output.item(name, label))?;
+    }
+    Ok(())
+}
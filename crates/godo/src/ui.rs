@@ -1,6 +1,6 @@
 use anyhow::Result;
 use godo_term::{Output, OutputError};
-use libgodo::{CleanupBatch, CleanupReport, GodoError, MergeStatus};
+use libgodo::{CleanupBatch, CleanupReport, CloneProgress, CloneStats, GodoError, MergeStatus};
 use std::result::Result as StdResult;
 
 /// Convert output-layer failures into domain errors.
@@ -48,11 +48,53 @@ pub fn prompt_select(output: &dyn Output, prompt: &str, options: Vec<String>) ->
     prompt_select_optional(output, prompt, options)?.ok_or_else(|| GodoError::UserAborted.into())
 }
 
+/// Format a byte count for humans using binary units (e.g. `1.5 GiB`).
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+/// Describe clone progress for a spinner message.
+pub fn clone_progress_message(progress: &CloneProgress) -> String {
+    format!(
+        "Cloning tree to sandbox... {}/{} files, {} of {} ({})",
+        progress.files_done,
+        progress.total_files,
+        format_bytes(progress.bytes_done),
+        format_bytes(progress.total_bytes),
+        progress.path.display()
+    )
+}
+
+/// Warn when a clone fell back to full copies for some files.
+pub fn warn_copied_files(output: &dyn Output, stats: &CloneStats) -> Result<()> {
+    if stats.copied_files == 0 {
+        return Ok(());
+    }
+    emit(output.warn(&format!(
+        "{} of {} files were fully copied ({} written); copy-on-write is not available on this \
+         filesystem",
+        stats.copied_files,
+        stats.copied_files + stats.reflinked_files,
+        format_bytes(stats.bytes_written)
+    )))
+}
+
 /// Render the cleanup report for a sandbox.
 pub fn render_cleanup_report(output: &dyn Output, report: CleanupReport) -> Result<()> {
     let status = report.status;
     let section = output.section(&format!("cleaning sandbox: {}", status.name));
-    let branch = format!("godo/{}", status.name);
+    let branch = &status.branch;
 
     if status.has_worktree && !status.has_uncommitted_changes && report.worktree_removed {
         emit(section.message("removed unmodified worktree"))?;
@@ -60,7 +102,9 @@ pub fn render_cleanup_report(output: &dyn Output, report: CleanupReport) -> Resu
         emit(section.message("skipping worktree with uncommitted changes"))?;
     }
 
-    if report.worktree_removed && report.branch_removed {
+    if report.worktree_removed && status.detached && !status.has_branch {
+        emit(section.success("detached sandbox cleaned up"))?;
+    } else if report.worktree_removed && report.branch_removed {
         emit(section.success("unmodified sandbox and branch cleaned up"))?;
     } else if report.worktree_removed && !report.branch_removed {
         emit(section.success(&format!("worktree removed, branch {branch} kept")))?;
@@ -34,13 +34,24 @@ pub fn current_sandbox_name(godo_dir: &Path) -> Result<Option<String>> {
         return Ok(None); // In godo dir but not in a sandbox
     }
 
-    // components[0] = project, components[1] = sandbox
-    let sandbox_name = components[1].as_os_str().to_string_lossy().to_string();
+    // components[0] = project, components[1] = sandbox or its namespace
+    let top = components[1].as_os_str().to_string_lossy().to_string();
 
     // Filter out meta-directories
-    if sandbox_name.starts_with(".godo-") {
+    if top.starts_with(".godo-") {
         return Ok(None);
     }
 
-    Ok(Some(sandbox_name))
+    // A namespaced sandbox such as `bug/1234` is the first nested directory holding a worktree.
+    let mut sandbox_dir = canonical_godo.join(components[0]);
+    let mut parts = Vec::new();
+    for component in &components[1..] {
+        sandbox_dir.push(component);
+        parts.push(component.as_os_str().to_string_lossy());
+        if sandbox_dir.join(".git").exists() {
+            return Ok(Some(parts.join("/")));
+        }
+    }
+
+    Ok(Some(top))
 }
@@ -56,6 +56,12 @@ pub fn create_repo(repo_name: &str) -> Result<(TempDir, PathBuf)> {
     Ok((temp_dir, repo_path))
 }
 
+/// Directory holding the sandboxes of the repository at `repo_path` inside `godo_dir`.
+pub fn project_dir(repo_path: &Path, godo_dir: &Path) -> Result<PathBuf> {
+    let godo = libgodo::Godo::new(godo_dir.to_path_buf(), Some(repo_path.to_path_buf()))?;
+    Ok(godo.project_dir()?)
+}
+
 /// Prepare a `Command` configured to run `godo` against the provided project.
 pub fn godo_command(repo_path: &Path, godo_dir: &Path) -> Command {
     let mut cmd = Command::new(godo_binary());
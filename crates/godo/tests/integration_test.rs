@@ -4,10 +4,14 @@
 
 mod common;
 
-use std::{fs, process::Command};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
 
 use anyhow::Result;
-use common::{create_repo, git, godo_binary, run_godo};
+use common::{create_repo, git, godo_binary, godo_command, project_dir, run_godo};
 use tempfile::TempDir;
 
 #[test]
@@ -29,7 +33,7 @@ fn test_project_directory_structure() -> Result<()> {
     }
 
     // Verify the directory structure
-    let project_dir = godo_dir.path().join("test-project");
+    let project_dir = project_dir(&repo_path, godo_dir.path())?;
     assert!(project_dir.exists(), "Project directory should exist");
 
     let sandbox_dir = project_dir.join("test-sandbox");
@@ -57,7 +61,12 @@ fn test_project_name_cleaning() -> Result<()> {
     }
 
     // Verify the cleaned project name
-    let project_dir = godo_dir.path().join("test-project-2024");
+    let project_dir = project_dir(&repo_path, godo_dir.path())?;
+    let dir_name = project_dir.file_name().unwrap().to_string_lossy();
+    assert!(
+        dir_name.starts_with("test-project-2024-"),
+        "Project directory should use the cleaned name: {dir_name}"
+    );
     assert!(
         project_dir.exists(),
         "Cleaned project directory should exist"
@@ -150,7 +159,7 @@ fn test_sandbox_list_from_within() -> Result<()> {
     )?;
     assert!(output.status.success(), "Creating sandbox should succeed");
 
-    let project_dir = godo_dir.path().join("test-project");
+    let project_dir = project_dir(&repo_path, godo_dir.path())?;
     let sandbox_dir = project_dir.join("test-sandbox");
     assert!(sandbox_dir.exists(), "Sandbox directory should exist");
 
@@ -188,7 +197,7 @@ fn test_sandbox_diff_from_within() -> Result<()> {
     )?;
     assert!(output.status.success(), "Creating sandbox should succeed");
 
-    let project_dir = godo_dir.path().join("test-project");
+    let project_dir = project_dir(&repo_path, godo_dir.path())?;
     let sandbox_dir = project_dir.join("test-sandbox");
 
     // Run godo diff (no name) from within the sandbox - should auto-detect
@@ -226,7 +235,7 @@ fn test_sandbox_run_self_blocked() -> Result<()> {
     )?;
     assert!(output.status.success(), "Creating sandbox should succeed");
 
-    let project_dir = godo_dir.path().join("test-project");
+    let project_dir = project_dir(&repo_path, godo_dir.path())?;
     let sandbox_dir = project_dir.join("test-sandbox");
 
     // Try to run the same sandbox from within it - should fail
@@ -279,7 +288,7 @@ fn test_sandbox_run_other_allowed() -> Result<()> {
         "Creating sandbox-one should succeed"
     );
 
-    let project_dir = godo_dir.path().join("test-project");
+    let project_dir = project_dir(&repo_path, godo_dir.path())?;
     let sandbox_one_dir = project_dir.join("sandbox-one");
 
     // Run a different sandbox from within sandbox-one - should succeed
@@ -326,7 +335,7 @@ fn test_sandbox_remove_self_blocked() -> Result<()> {
     )?;
     assert!(output.status.success(), "Creating sandbox should succeed");
 
-    let project_dir = godo_dir.path().join("test-project");
+    let project_dir = project_dir(&repo_path, godo_dir.path())?;
     let sandbox_dir = project_dir.join("test-sandbox");
 
     // Try to remove self from within - should fail
@@ -382,7 +391,7 @@ fn test_sandbox_remove_other_allowed() -> Result<()> {
     )?;
     assert!(output.status.success());
 
-    let project_dir = godo_dir.path().join("test-project");
+    let project_dir = project_dir(&repo_path, godo_dir.path())?;
     let sandbox_one_dir = project_dir.join("sandbox-one");
 
     // Remove sandbox-two from within sandbox-one - should succeed
@@ -427,7 +436,7 @@ fn test_sandbox_clean_all_blocked() -> Result<()> {
     )?;
     assert!(output.status.success(), "Creating sandbox should succeed");
 
-    let project_dir = godo_dir.path().join("test-project");
+    let project_dir = project_dir(&repo_path, godo_dir.path())?;
     let sandbox_dir = project_dir.join("test-sandbox");
 
     // Try to clean all from within - should fail
@@ -475,7 +484,7 @@ fn test_sandbox_clean_self_blocked() -> Result<()> {
     )?;
     assert!(output.status.success(), "Creating sandbox should succeed");
 
-    let project_dir = godo_dir.path().join("test-project");
+    let project_dir = project_dir(&repo_path, godo_dir.path())?;
     let sandbox_dir = project_dir.join("test-sandbox");
 
     // Try to clean self from within - should fail
@@ -531,7 +540,7 @@ fn test_sandbox_clean_other_allowed() -> Result<()> {
     )?;
     assert!(output.status.success());
 
-    let project_dir = godo_dir.path().join("test-project");
+    let project_dir = project_dir(&repo_path, godo_dir.path())?;
     let sandbox_one_dir = project_dir.join("sandbox-one");
 
     // Clean sandbox-two from within sandbox-one - should succeed
@@ -587,7 +596,7 @@ fn test_clean_branch_option_with_uncommitted_changes() -> Result<()> {
     }
 
     // Verify sandbox was created
-    let project_dir = godo_dir.path().join("test-project");
+    let project_dir = project_dir(&repo_path, godo_dir.path())?;
     let sandbox_dir = project_dir.join("test-sandbox");
     assert!(sandbox_dir.exists(), "Sandbox directory should exist");
 
@@ -606,3 +615,593 @@ fn test_clean_branch_option_with_uncommitted_changes() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_project_config_applies_defaults() -> Result<()> {
+    let (_temp_dir, repo_path) = create_repo("test-project")?;
+    let godo_dir = TempDir::new()?;
+
+    fs::write(
+        repo_path.join(".godo.toml"),
+        "branch_prefix = \"team\"\n\n[run]\nexcludes = [\"*.log\"]\nafter = \"keep\"\n",
+    )?;
+    fs::write(repo_path.join(".gitignore"), "*.log\n")?;
+    git(&repo_path, &["add", ".godo.toml", ".gitignore"])?;
+    git(&repo_path, &["commit", "-m", "Add godo config"])?;
+    fs::write(repo_path.join("build.log"), "noise")?;
+
+    // No --keep: the project config asks for sandboxes to be kept.
+    let output = run_godo(
+        &repo_path,
+        godo_dir.path(),
+        &["run", "test-sandbox", "echo", "test"],
+    )?;
+
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("godo run failed");
+    }
+
+    let sandbox_dir = project_dir(&repo_path, godo_dir.path())?.join("test-sandbox");
+    assert!(sandbox_dir.exists(), "Sandbox should be kept per config");
+    assert!(
+        !sandbox_dir.join("build.log").exists(),
+        "Configured exclude should be applied"
+    );
+
+    let branches = git(&repo_path, &["branch", "--list", "team/test-sandbox"])?;
+    assert!(
+        String::from_utf8_lossy(&branches.stdout).contains("team/test-sandbox"),
+        "Sandbox branch should use the configured prefix"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_fork_copies_sandbox_working_tree() -> Result<()> {
+    let (_temp_dir, repo_path) = create_repo("test-project")?;
+    let godo_dir = TempDir::new()?;
+
+    // Leave an untracked artifact behind in the source sandbox.
+    let output = run_godo(
+        &repo_path,
+        godo_dir.path(),
+        &["run", "--keep", "--sh", "warm", "echo built > artifact.txt"],
+    )?;
+    assert!(output.status.success(), "godo run should succeed");
+
+    let output = run_godo(&repo_path, godo_dir.path(), &["fork", "warm", "experiment"])?;
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("godo fork failed");
+    }
+
+    let fork_dir = project_dir(&repo_path, godo_dir.path())?.join("experiment");
+    assert_eq!(
+        fs::read_to_string(fork_dir.join("artifact.txt"))?,
+        "built\n"
+    );
+
+    let branches = git(&repo_path, &["branch", "--list", "godo/experiment"])?;
+    assert!(
+        String::from_utf8_lossy(&branches.stdout).contains("godo/experiment"),
+        "Fork should get its own branch"
+    );
+
+    Ok(())
+}
+
+/// Create a kept sandbox that includes uncommitted changes, returning its path.
+fn create_including_sandbox(
+    repo_path: &Path,
+    godo_dir: &Path,
+    extra_args: &[&str],
+) -> Result<PathBuf> {
+    let mut args = vec!["run", "--keep", "--uncommitted", "include"];
+    args.extend_from_slice(extra_args);
+    args.extend_from_slice(&["mirror", "true"]);
+    let output = run_godo(repo_path, godo_dir, &args)?;
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("godo run failed");
+    }
+    Ok(project_dir(repo_path, godo_dir)?.join("mirror"))
+}
+
+/// Short-format `git status` output, including untracked files individually.
+fn status(repo_path: &Path) -> Result<String> {
+    let output = git(
+        repo_path,
+        &["status", "--porcelain", "--untracked-files=all"],
+    )?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[test]
+fn test_include_mirrors_deletions_and_renames() -> Result<()> {
+    let (_temp_dir, repo_path) = create_repo("test-project")?;
+    let godo_dir = TempDir::new()?;
+
+    fs::create_dir_all(repo_path.join("src/nested"))?;
+    for file in ["gone.txt", "staged-gone.txt", "moved.txt", "renamed.txt"] {
+        fs::write(repo_path.join(file), file)?;
+    }
+    fs::write(repo_path.join("src/nested/deep.rs"), "deep")?;
+    fs::write(repo_path.join("src/kept.rs"), "kept")?;
+    git(&repo_path, &["add", "."])?;
+    git(&repo_path, &["commit", "-m", "Add files"])?;
+
+    // Unstaged and staged deletions, including a whole directory.
+    fs::remove_file(repo_path.join("gone.txt"))?;
+    fs::remove_dir_all(repo_path.join("src/nested"))?;
+    git(&repo_path, &["rm", "--quiet", "staged-gone.txt"])?;
+    // A rename done behind Git's back, and one staged with `git mv`.
+    fs::rename(repo_path.join("moved.txt"), repo_path.join("moved-new.txt"))?;
+    git(&repo_path, &["mv", "renamed.txt", "src/renamed.txt"])?;
+
+    let expected = status(&repo_path)?;
+    assert!(expected.contains(" D gone.txt"), "{expected}");
+    assert!(
+        expected.contains("R  renamed.txt -> src/renamed.txt"),
+        "{expected}"
+    );
+
+    let sandbox_dir = create_including_sandbox(&repo_path, godo_dir.path(), &[])?;
+    assert_eq!(status(&sandbox_dir)?, expected);
+    assert!(!sandbox_dir.join("gone.txt").exists());
+    assert!(!sandbox_dir.join("src/nested").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_include_mirrors_deletions_under_excluded_paths() -> Result<()> {
+    let (_temp_dir, repo_path) = create_repo("test-project")?;
+    let godo_dir = TempDir::new()?;
+
+    fs::create_dir_all(repo_path.join("vendor"))?;
+    fs::write(repo_path.join("vendor/kept.c"), "kept")?;
+    fs::write(repo_path.join("vendor/gone.c"), "gone")?;
+    git(&repo_path, &["add", "."])?;
+    git(&repo_path, &["commit", "-m", "Vendor"])?;
+    fs::remove_file(repo_path.join("vendor/gone.c"))?;
+
+    let sandbox_dir =
+        create_including_sandbox(&repo_path, godo_dir.path(), &["--exclude", "vendor/"])?;
+    assert_eq!(status(&sandbox_dir)?, status(&repo_path)?);
+    assert_eq!(
+        fs::read_to_string(sandbox_dir.join("vendor/kept.c"))?,
+        "kept"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_detached_sandbox_has_no_branch() -> Result<()> {
+    let (_temp_dir, repo_path) = create_repo("test-project")?;
+    let godo_dir = TempDir::new()?;
+
+    let output = run_godo(
+        &repo_path,
+        godo_dir.path(),
+        &["run", "--keep", "--detached", "scratch", "echo", "test"],
+    )?;
+    if !output.status.success() {
+        eprintln!("stdout: {}", String::from_utf8_lossy(&output.stdout));
+        eprintln!("stderr: {}", String::from_utf8_lossy(&output.stderr));
+        panic!("godo run failed");
+    }
+
+    let sandbox_dir = project_dir(&repo_path, godo_dir.path())?.join("scratch");
+    assert!(sandbox_dir.exists(), "Sandbox directory should exist");
+    let branches = git(&repo_path, &["branch", "--list", "godo/*"])?;
+    assert!(
+        branches.stdout.is_empty(),
+        "Detached sandbox should not create a branch"
+    );
+
+    // Committing is refused before the command runs.
+    let output = run_godo(
+        &repo_path,
+        godo_dir.path(),
+        &["run", "--commit", "msg", "scratch", "touch", "new.txt"],
+    )?;
+    assert!(!output.status.success());
+    assert!(!sandbox_dir.join("new.txt").exists());
+
+    // Cleanup removes it without looking for a branch to merge.
+    let output = run_godo(&repo_path, godo_dir.path(), &["--no-prompt", "clean"])?;
+    assert!(output.status.success());
+    assert!(
+        !sandbox_dir.exists(),
+        "Clean should remove the detached sandbox"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_auto_named_sandboxes_are_unique() -> Result<()> {
+    let (_temp_dir, repo_path) = create_repo("test-project")?;
+    let godo_dir = TempDir::new()?;
+
+    // Parallel jobs race for names; each must get its own sandbox.
+    let children = (0..4)
+        .map(|_| {
+            godo_command(&repo_path, godo_dir.path())
+                .args(["--no-prompt", "run", "--keep", "-", "echo", "test"])
+                .stdout(Stdio::null())
+                .spawn()
+        })
+        .collect::<Vec<_>>();
+    for child in children {
+        assert!(child?.wait()?.success());
+    }
+
+    // With --auto-name, every positional argument belongs to the command.
+    let output = run_godo(
+        &repo_path,
+        godo_dir.path(),
+        &[
+            "--no-prompt",
+            "run",
+            "--keep",
+            "--auto-name",
+            "touch",
+            "marker.txt",
+        ],
+    )?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let name = stdout
+        .split_whitespace()
+        .skip_while(|word| *word != "sandbox")
+        .nth(1)
+        .expect("creation message should name the sandbox");
+
+    let project_dir = project_dir(&repo_path, godo_dir.path())?;
+    assert!(project_dir.join(name).join("marker.txt").exists());
+    let sandboxes = fs::read_dir(&project_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .count();
+    assert_eq!(sandboxes, 5, "Each run should create a distinct sandbox");
+
+    Ok(())
+}
+
+#[test]
+fn test_namespaced_sandboxes() -> Result<()> {
+    let (_temp_dir, repo_path) = create_repo("test-project")?;
+    let godo_dir = TempDir::new()?;
+
+    for name in ["bug/1234", "feature"] {
+        let output = run_godo(
+            &repo_path,
+            godo_dir.path(),
+            &["run", "--keep", name, "echo", "test"],
+        )?;
+        assert!(output.status.success(), "Creating {name} should succeed");
+    }
+    let sandbox_dir = project_dir(&repo_path, godo_dir.path())?
+        .join("bug")
+        .join("1234");
+    assert!(sandbox_dir.exists(), "Namespaced sandbox should be nested");
+
+    let output = run_godo(&repo_path, godo_dir.path(), &["list", "bug/"])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(
+        stdout.contains("bug/1234"),
+        "Listing should show bug/1234: {stdout}"
+    );
+    assert!(
+        !stdout.contains("feature"),
+        "Prefix should filter: {stdout}"
+    );
+    assert!(
+        !stdout.contains("disk") && !stdout.contains("total"),
+        "Disk usage should only be measured with --size: {stdout}"
+    );
+
+    let output = run_godo(&repo_path, godo_dir.path(), &["list", "--size", "bug/"])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success());
+    assert!(
+        stdout.contains("disk") && stdout.contains("total"),
+        "--size should show disk usage: {stdout}"
+    );
+
+    // The nested sandbox is recognised from inside, so running it from itself is refused.
+    fs::create_dir_all(sandbox_dir.join("src"))?;
+    let output = Command::new(godo_binary())
+        .current_dir(sandbox_dir.join("src"))
+        .args([
+            "--dir",
+            godo_dir.path().to_str().unwrap(),
+            "--repo-dir",
+            repo_path.to_str().unwrap(),
+            "run",
+            "bug/1234",
+            "echo",
+            "test",
+        ])
+        .output()?;
+    assert!(!output.status.success());
+    let combined = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        combined.contains("Cannot run sandbox 'bug/1234' from within itself"),
+        "Should show self-run error, got: {combined}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_list_all_flags_and_prunes_stale_projects() -> Result<()> {
+    let (_temp_dir, repo_path) = create_repo("test-project")?;
+    let (_other_temp_dir, other_repo) = create_repo("other-project")?;
+    let godo_dir = TempDir::new()?;
+
+    for repo in [&repo_path, &other_repo] {
+        let output = run_godo(
+            repo,
+            godo_dir.path(),
+            &["run", "--keep", "box", "echo", "test"],
+        )?;
+        assert!(output.status.success(), "Creating sandbox should succeed");
+    }
+    let other_project_dir = project_dir(&other_repo, godo_dir.path())?;
+    fs::remove_dir_all(&other_repo)?;
+
+    let output = run_godo(&repo_path, godo_dir.path(), &["list", "--all"])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Listing should succeed: {stdout}");
+    assert!(
+        stdout.contains("test-project-"),
+        "Should list this project: {stdout}"
+    );
+    assert!(
+        stdout.contains("other-project-"),
+        "Should list the other project: {stdout}"
+    );
+    assert!(
+        stdout.contains("repository no longer exists"),
+        "Should flag the stale project: {stdout}"
+    );
+    assert!(other_project_dir.exists(), "Listing alone should not prune");
+
+    let output = run_godo(
+        &repo_path,
+        godo_dir.path(),
+        &["--no-prompt", "list", "--all", "--prune"],
+    )?;
+    assert!(output.status.success(), "Pruning should succeed");
+    assert!(
+        !other_project_dir.exists(),
+        "Stale project should be pruned"
+    );
+    assert!(
+        project_dir(&repo_path, godo_dir.path())?
+            .join("box")
+            .exists(),
+        "Live sandboxes should be kept"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_moved_repository_keeps_its_sandboxes() -> Result<()> {
+    let (temp_dir, repo_path) = create_repo("test-project")?;
+    let godo_dir = TempDir::new()?;
+
+    let output = run_godo(
+        &repo_path,
+        godo_dir.path(),
+        &["run", "--keep", "box", "echo", "test"],
+    )?;
+    assert!(output.status.success(), "Creating sandbox should succeed");
+
+    let moved_repo = temp_dir.path().join("moved-project");
+    fs::rename(&repo_path, &moved_repo)?;
+
+    // Listing changes nothing and points at `godo repair`.
+    let output = run_godo(&moved_repo, godo_dir.path(), &["list"])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Listing should succeed: {stdout}");
+    assert!(
+        stdout.contains("godo repair"),
+        "Listing should suggest a repair: {stdout}"
+    );
+    assert!(
+        !project_dir(&moved_repo, godo_dir.path())?
+            .join("box")
+            .exists(),
+        "Listing should not move sandboxes"
+    );
+
+    let output = run_godo(&moved_repo, godo_dir.path(), &["repair"])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Repair should succeed: {stdout}");
+    assert!(
+        stdout.contains("box"),
+        "Repair should report the sandbox: {stdout}"
+    );
+    assert!(
+        project_dir(&moved_repo, godo_dir.path())?
+            .join("box")
+            .exists(),
+        "Sandbox should live in the new project directory"
+    );
+
+    let output = run_godo(&moved_repo, godo_dir.path(), &["list"])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("box") && !stdout.contains("dangling"),
+        "Sandbox should follow the repository: {stdout}"
+    );
+    assert!(
+        !stdout.contains("godo repair"),
+        "No repair should be left: {stdout}"
+    );
+
+    // Commands that change sandboxes repair first.
+    let moved_again = temp_dir.path().join("moved-again");
+    fs::rename(&moved_repo, &moved_again)?;
+    let output = run_godo(
+        &moved_again,
+        godo_dir.path(),
+        &["--no-prompt", "run", "--keep", "box", "true"],
+    )?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Run should succeed: {stdout}");
+    assert!(
+        project_dir(&moved_again, godo_dir.path())?
+            .join("box")
+            .exists(),
+        "Run should reuse the repaired sandbox"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_run_records_command_and_exit_code() -> Result<()> {
+    let (_temp_dir, repo_path) = create_repo("test-project")?;
+    let godo_dir = TempDir::new()?;
+
+    let output = run_godo(
+        &repo_path,
+        godo_dir.path(),
+        &["run", "--keep", "box", "false"],
+    )?;
+    assert_eq!(output.status.code(), Some(1), "Exit code should propagate");
+
+    let godo = libgodo::Godo::new(godo_dir.path().to_path_buf(), Some(repo_path))?;
+    let metadata = godo.metadata("box")?.expect("metadata should be recorded");
+    assert_eq!(metadata.command, vec!["false"]);
+    assert_eq!(metadata.last_exit_code, Some(1));
+    assert!(metadata.last_used_at.is_some());
+
+    Ok(())
+}
+
+#[test]
+fn test_untrusted_project_hooks_are_skipped_without_prompt() -> Result<()> {
+    let (_temp_dir, repo_path) = create_repo("test-project")?;
+    let godo_dir = TempDir::new()?;
+    fs::write(
+        repo_path.join(".godo.toml"),
+        "[hooks]\nsetup = [\"touch hooked\"]\n",
+    )?;
+    let project_dir = project_dir(&repo_path, godo_dir.path())?;
+
+    let output = run_godo(
+        &repo_path,
+        godo_dir.path(),
+        &["--no-prompt", "run", "--keep", "untrusted", "true"],
+    )?;
+    assert!(output.status.success(), "Run should succeed");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("--trust-hooks"));
+    assert!(!project_dir.join("untrusted/hooked").exists());
+
+    let output = run_godo(
+        &repo_path,
+        godo_dir.path(),
+        &[
+            "--no-prompt",
+            "run",
+            "--keep",
+            "--trust-hooks",
+            "trusted",
+            "true",
+        ],
+    )?;
+    assert!(output.status.success(), "Run should succeed");
+    assert!(project_dir.join("trusted/hooked").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_configured_commit_is_skipped_without_prompt() -> Result<()> {
+    let (_temp_dir, repo_path) = create_repo("test-project")?;
+    let godo_dir = TempDir::new()?;
+    fs::write(repo_path.join(".godo.toml"), "[run]\nafter = \"commit\"\n")?;
+
+    // Writing a commit message needs an editor, so the sandbox is kept instead.
+    let output = run_godo(
+        &repo_path,
+        godo_dir.path(),
+        &["--no-prompt", "run", "configured", "touch", "new.txt"],
+    )?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Run should succeed: {stdout}");
+    assert!(
+        stdout.contains("--commit"),
+        "Should suggest --commit: {stdout}"
+    );
+    let sandbox_dir = project_dir(&repo_path, godo_dir.path())?.join("configured");
+    assert!(
+        sandbox_dir.join("new.txt").exists(),
+        "Sandbox should be kept"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_broken_config_only_blocks_commands_that_use_it() -> Result<()> {
+    let (_temp_dir, repo_path) = create_repo("test-project")?;
+    let (_other_temp_dir, other_repo) = create_repo("other-project")?;
+    let godo_dir = TempDir::new()?;
+    for repo in [&repo_path, &other_repo] {
+        let output = run_godo(
+            repo,
+            godo_dir.path(),
+            &["run", "--keep", "box", "echo", "test"],
+        )?;
+        assert!(output.status.success(), "Creating sandbox should succeed");
+    }
+    fs::write(repo_path.join(".godo.toml"), "[run\n")?;
+
+    let output = run_godo(&repo_path, godo_dir.path(), &["list"])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Listing should succeed: {stdout}");
+    assert!(
+        stdout.contains("Ignoring configuration") && stdout.contains("box"),
+        "Should warn and still list: {stdout}"
+    );
+
+    let output = run_godo(
+        &repo_path,
+        godo_dir.path(),
+        &["run", "--keep", "other", "true"],
+    )?;
+    assert!(!output.status.success(), "Running should need the config");
+
+    // One project's broken config is reported in its own section.
+    let output = run_godo(&other_repo, godo_dir.path(), &["list", "--all"])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "Listing should succeed: {stdout}");
+    assert!(
+        stdout.contains("invalid configuration"),
+        "Should flag the broken project: {stdout}"
+    );
+    assert!(
+        stdout.contains("other-project-"),
+        "Should still list the other project: {stdout}"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,361 @@
+#[cfg(unix)]
+use std::os::unix::fs::symlink;
+#[cfg(windows)]
+use std::os::windows::fs::{symlink_dir, symlink_file};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    thread,
+};
+
+use crate::{
+    error::{GodoError, Result, best_effort},
+    excludes::ExcludeSet,
+    types::{CloneProgress, CloneStats},
+};
+
+/// File name used for the temporary copy-on-write probe in the destination directory.
+const PROBE_FILE_NAME: &str = ".godo-reflink-probe";
+
+/// A single file, symlink or directory to clone from the source tree into the sandbox.
+pub struct CloneJob {
+    /// Path of the entry in the source tree.
+    pub src: PathBuf,
+    /// Destination path inside the sandbox. Must not exist yet.
+    pub dest: PathBuf,
+    /// Repository-relative path of the entry, used to scope exclude patterns.
+    pub entry: String,
+}
+
+/// A regular file found by the walk, waiting to be reflinked or copied by a worker.
+struct FileCopy {
+    /// Path of the file in the source tree.
+    src: PathBuf,
+    /// Destination path inside the sandbox.
+    dest: PathBuf,
+    /// Repository-relative path of the file.
+    path: PathBuf,
+    /// Size of the file in bytes.
+    bytes: u64,
+}
+
+/// Message sent to the coordinating thread by the walker and the copy workers.
+enum Message {
+    /// The walk found a file of this many bytes to clone.
+    Found(u64),
+    /// A file was cloned.
+    Cloned {
+        /// Repository-relative path of the file.
+        path: PathBuf,
+        /// Size of the file in bytes.
+        bytes: u64,
+        /// Bytes written by a full copy, or `None` when the file was reflinked.
+        written: Option<u64>,
+    },
+    /// Walking or cloning failed.
+    Failed(GodoError),
+}
+
+/// Check whether files can be reflinked from `src_root` into `dest_dir`.
+///
+/// A file from the source tree is reflinked into a temporary file in `dest_dir`. Returns
+/// `None` when there is no readable file to probe with, so support is unknown.
+pub fn reflink_supported(src_root: &Path, dest_dir: &Path) -> Result<Option<bool>> {
+    let Some(probe_src) = find_probe_file(src_root) else {
+        return Ok(None);
+    };
+    let probe_dest = dest_dir.join(format!("{PROBE_FILE_NAME}-{}", process::id()));
+    let supported = reflink_copy::reflink(&probe_src, &probe_dest).is_ok();
+    if probe_dest.exists() {
+        fs::remove_file(&probe_dest)?;
+    }
+    Ok(Some(supported))
+}
+
+/// Find a regular file below `root` (outside `.git`) to use for the reflink probe.
+///
+/// Unreadable directories are skipped, so one of them does not end the search early.
+fn find_probe_file(root: &Path) -> Option<PathBuf> {
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            if entry.file_name() == ".git" {
+                continue;
+            }
+            match entry.file_type() {
+                Ok(kind) if kind.is_file() => return Some(entry.path()),
+                Ok(kind) if kind.is_dir() => pending.push(entry.path()),
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+/// Clone `jobs` in parallel, reporting progress through `on_progress`.
+///
+/// One thread walks the jobs, creating directories and symlinks and queueing each file for a
+/// pool of workers that reflink or copy it, so large directories are cloned in parallel too.
+/// Progress is reported after every file, on the calling thread; the totals grow until the walk
+/// is over. After the first failure no new files are started and that error is returned.
+///
+/// Every file reports whether it was reflinked or copied, so the returned counts are exact.
+/// `reflink_supported` is the result of the filesystem probe, reported alongside them.
+pub fn clone_all(
+    jobs: &[CloneJob],
+    excludes: &ExcludeSet,
+    reflink_supported: bool,
+    on_progress: &mut dyn FnMut(CloneProgress),
+) -> Result<CloneStats> {
+    let mut stats = CloneStats {
+        copy_on_write: reflink_supported,
+        ..CloneStats::default()
+    };
+    if jobs.is_empty() {
+        return Ok(stats);
+    }
+
+    let mut progress = CloneProgress {
+        files_done: 0,
+        total_files: 0,
+        bytes_done: 0,
+        total_bytes: 0,
+        path: PathBuf::new(),
+    };
+    let workers = thread::available_parallelism().map_or(4, |count| count.get());
+    let failed = AtomicBool::new(false);
+    let mut first_error = None;
+    let (queue_sender, queue) = mpsc::channel();
+    let queue = Mutex::new(queue);
+
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+
+        let walk_sender = sender.clone();
+        let failed_ref = &failed;
+        scope.spawn(move || {
+            if let Err(err) = walk(jobs, excludes, failed_ref, &queue_sender, &walk_sender) {
+                failed_ref.store(true, Ordering::Relaxed);
+                best_effort(walk_sender.send(Message::Failed(err)));
+            }
+        });
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let (queue, failed) = (&queue, &failed);
+            scope.spawn(move || {
+                while let Some(file) = next_file(queue) {
+                    if failed.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let message = clone_file(file).unwrap_or_else(|err| {
+                        failed.store(true, Ordering::Relaxed);
+                        Message::Failed(err)
+                    });
+                    if sender.send(message).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        for message in receiver {
+            match message {
+                Message::Found(bytes) => {
+                    progress.total_files += 1;
+                    progress.total_bytes += bytes;
+                }
+                Message::Cloned {
+                    path,
+                    bytes,
+                    written,
+                } => {
+                    match written {
+                        Some(written) => {
+                            stats.copied_files += 1;
+                            stats.bytes_written += written;
+                        }
+                        None => stats.reflinked_files += 1,
+                    }
+                    progress.files_done += 1;
+                    progress.bytes_done += bytes;
+                    progress.path = path;
+                    on_progress(progress.clone());
+                }
+                Message::Failed(err) => {
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+    });
+
+    first_error.map_or(Ok(stats), Err)
+}
+
+/// Walk `jobs`, creating directories and symlinks in the sandbox and queueing files on `queue`.
+///
+/// Each file is announced on `sender` before it is queued, so the totals always cover the
+/// files reported done. Every directory exists before any file inside it is queued. The walk
+/// stops early once `failed` is set.
+fn walk(
+    jobs: &[CloneJob],
+    excludes: &ExcludeSet,
+    failed: &AtomicBool,
+    queue: &Sender<FileCopy>,
+    sender: &Sender<Message>,
+) -> Result<()> {
+    for job in jobs {
+        let mut pending = vec![(job.src.clone(), job.dest.clone(), PathBuf::from(&job.entry))];
+        while let Some((src, dest, path)) = pending.pop() {
+            if failed.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            let metadata = fs::symlink_metadata(&src)?;
+            if metadata.is_dir() {
+                fs::create_dir_all(&dest)?;
+                for child in fs::read_dir(&src)? {
+                    let child = child?;
+                    let child_path = path.join(child.file_name());
+                    let is_dir = child.file_type()?.is_dir();
+                    if !excludes.excludes_path(&child_path, is_dir) {
+                        pending.push((child.path(), dest.join(child.file_name()), child_path));
+                    }
+                }
+            } else if metadata.is_symlink() {
+                clone_symlink(&src, &dest)?;
+            } else {
+                let bytes = metadata.len();
+                let file = FileCopy {
+                    src,
+                    dest,
+                    path,
+                    bytes,
+                };
+                // Sending only fails once the coordinator has stopped listening.
+                if sender.send(Message::Found(bytes)).is_err() || queue.send(file).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Take the next file from the shared queue, or `None` once the walk is over and the queue is
+/// drained.
+fn next_file(queue: &Mutex<Receiver<FileCopy>>) -> Option<FileCopy> {
+    queue.lock().ok()?.recv().ok()
+}
+
+/// Reflink or copy a single file, reporting how it was materialised.
+///
+/// Each file is reflinked on its own, so one that cannot be is copied without affecting the
+/// others.
+fn clone_file(file: FileCopy) -> Result<Message> {
+    let written = reflink_copy::reflink_or_copy(&file.src, &file.dest).map_err(|e| {
+        GodoError::OperationError(format!("Failed to copy {:?} to sandbox: {e}", file.path))
+    })?;
+    Ok(Message::Cloned {
+        path: file.path,
+        bytes: file.bytes,
+        written,
+    })
+}
+
+/// Recreate the symlink `src` at `dest`, pointing at the same target.
+fn clone_symlink(src: &Path, dest: &Path) -> Result<()> {
+    let target = fs::read_link(src)?;
+    #[cfg(unix)]
+    symlink(&target, dest)?;
+    #[cfg(windows)]
+    {
+        if target.is_dir() {
+            symlink_dir(&target, dest)?;
+        } else {
+            symlink_file(&target, dest)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn reflink_support_is_unknown_without_a_file_to_probe() {
+        let tmp = tempdir().unwrap();
+        let src = tmp.path().join("src");
+        fs::create_dir_all(src.join(".git")).unwrap();
+        fs::create_dir_all(src.join("empty")).unwrap();
+        fs::write(src.join(".git/HEAD"), "ref: refs/heads/main").unwrap();
+
+        assert_eq!(reflink_supported(&src, tmp.path()).unwrap(), None);
+
+        fs::write(src.join("empty/file"), "data").unwrap();
+        assert!(reflink_supported(&src, tmp.path()).unwrap().is_some());
+        assert_eq!(fs::read_dir(tmp.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn clone_all_reports_progress_for_every_file() {
+        let tmp = tempdir().unwrap();
+        let src = tmp.path().join("src");
+        let dest = tmp.path().join("dest");
+        fs::create_dir_all(src.join("lib/target")).unwrap();
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(src.join("lib/a.rs"), "aaaa").unwrap();
+        fs::write(src.join("lib/target/out"), "excluded").unwrap();
+        fs::write(src.join("README.md"), "readme").unwrap();
+
+        let excludes = ExcludeSet::new(&src, &["target/".to_string()]).unwrap();
+        let jobs: Vec<CloneJob> = ["lib", "README.md"]
+            .iter()
+            .map(|entry| CloneJob {
+                src: src.join(entry),
+                dest: dest.join(entry),
+                entry: entry.to_string(),
+            })
+            .collect();
+
+        let mut events = Vec::new();
+        let stats = clone_all(&jobs, &excludes, false, &mut |progress| {
+            events.push(progress)
+        })
+        .unwrap();
+
+        // Files inside a directory report their own progress.
+        let mut paths: Vec<PathBuf> = events.iter().map(|event| event.path.clone()).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [PathBuf::from("README.md"), PathBuf::from("lib/a.rs")]
+        );
+        assert!(
+            events
+                .iter()
+                .all(|event| event.files_done <= event.total_files)
+        );
+        let last = events.last().unwrap();
+        assert_eq!((last.files_done, last.total_files), (2, 2));
+        assert_eq!((last.bytes_done, last.total_bytes), (10, 10));
+        assert_eq!(stats.reflinked_files + stats.copied_files, 2);
+        // Bytes are only written for files that were actually copied.
+        assert_eq!(stats.copied_files == 0, stats.bytes_written == 0);
+        assert!(stats.bytes_written <= 10);
+        assert_eq!(fs::read_to_string(dest.join("lib/a.rs")).unwrap(), "aaaa");
+        assert!(dest.join("README.md").exists());
+        assert!(!dest.join("lib/target").exists());
+    }
+}
@@ -1,4 +1,5 @@
 use std::{io, path::PathBuf, result::Result as StdResult};
+
 use thiserror::Error;
 
 /// Custom Result type for Godo operations.
@@ -54,6 +55,26 @@ pub enum GodoError {
         repo_dir: PathBuf,
     },
 
+    /// Copy-on-write cloning was required but could not be confirmed to work.
+    #[error("Copy-on-write cloning could not be confirmed from {repo_dir} into {godo_dir}")]
+    CopyOnWriteUnavailable {
+        /// Root of the source repository.
+        repo_dir: PathBuf,
+        /// Directory the sandbox would be created in.
+        godo_dir: PathBuf,
+    },
+
+    /// A setup hook failed while preparing a new sandbox.
+    #[error("Setup hook '{command}' {status}\n{output}")]
+    HookFailed {
+        /// The hook command line.
+        command: String,
+        /// Description of how the hook failed.
+        status: String,
+        /// Combined stdout and stderr captured from the hook.
+        output: String,
+    },
+
     /// An underlying I/O operation failed.
     #[error("IO error: {0}")]
     IoError(#[from] io::Error),
@@ -67,9 +88,19 @@ impl GodoError {
             Self::UserAborted => 130,
             Self::SandboxError { .. } => 2,
             Self::UncommittedChanges { .. } => 2,
+            Self::HookFailed { .. } => 2,
+            Self::CopyOnWriteUnavailable { .. } => 2,
             Self::BaseError { .. } => 3,
             Self::GitError(_) => 4,
             _ => 1,
         }
     }
 }
+
+/// Discard the outcome of a best-effort step, such as cleanup after an earlier failure.
+///
+/// The caller carries on whether or not the step worked, and a failure here would only hide
+/// the error that is worth reporting.
+pub fn best_effort<T, E>(result: StdResult<T, E>) {
+    drop(result);
+}
@@ -0,0 +1,317 @@
+use std::path::{Component, Path, PathBuf};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+use crate::error::{GodoError, Result};
+
+/// Compiled set of gitignore-style exclude patterns applied when cloning a sandbox.
+///
+/// Every path is matched here as the clone walks the source tree. For nested repositories such
+/// as submodules, the patterns that can match inside them are rebased onto their roots.
+///
+/// A sparse sandbox additionally excludes everything outside its cone, using the rules of
+/// `git sparse-checkout` cone mode: everything below a sparse path, plus the files directly
+/// inside the root and each directory leading to a sparse path.
+pub struct ExcludeSet {
+    /// Matcher for the patterns rooted at the source repository.
+    matcher: Gitignore,
+    /// The raw patterns, used to rebase them onto nested repositories.
+    patterns: Vec<String>,
+    /// Directories a sparse sandbox is restricted to; empty for a full sandbox.
+    sparse: Vec<PathBuf>,
+}
+
+impl ExcludeSet {
+    /// Compile `patterns` relative to the repository `root`.
+    pub fn new(root: &Path, patterns: &[String]) -> Result<Self> {
+        let mut builder = GitignoreBuilder::new(root);
+        let mut cleaned = Vec::new();
+        for pattern in patterns {
+            let pattern = pattern.trim();
+            if pattern.is_empty() {
+                continue;
+            }
+            if pattern.starts_with('!') {
+                return Err(GodoError::OperationError(format!(
+                    "Invalid exclude pattern '{pattern}': negated patterns are not supported"
+                )));
+            }
+            builder.add_line(None, pattern).map_err(|e| {
+                GodoError::OperationError(format!("Invalid exclude pattern '{pattern}': {e}"))
+            })?;
+            cleaned.push(pattern.to_string());
+        }
+
+        let matcher = builder
+            .build()
+            .map_err(|e| GodoError::OperationError(format!("Invalid exclude patterns: {e}")))?;
+
+        Ok(Self {
+            matcher,
+            patterns: cleaned,
+            sparse: Vec::new(),
+        })
+    }
+
+    /// Restrict the set to the sparse cone of `paths`, given relative to the repository root.
+    pub fn with_sparse_paths(mut self, paths: &[String]) -> Result<Self> {
+        let mut sparse = Vec::new();
+        for path in paths {
+            let normalized: PathBuf = Path::new(path)
+                .components()
+                .filter(|component| !matches!(component, Component::CurDir))
+                .collect();
+            let relative = normalized
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+            if !relative || normalized.as_os_str().is_empty() {
+                return Err(GodoError::OperationError(format!(
+                    "Invalid sparse path '{path}': must be a directory inside the repository"
+                )));
+            }
+            sparse.push(normalized);
+        }
+        // Paths inside another sparse path add nothing to the cone.
+        self.sparse = sparse
+            .iter()
+            .filter(|path| {
+                !sparse
+                    .iter()
+                    .any(|other| other != *path && path.starts_with(other))
+            })
+            .cloned()
+            .collect();
+        self.sparse.sort();
+        self.sparse.dedup();
+        Ok(self)
+    }
+
+    /// Directories the sandbox is restricted to, or an empty slice for a full sandbox.
+    pub fn sparse_paths(&self) -> &[PathBuf] {
+        &self.sparse
+    }
+
+    /// Whether the repository-relative `path` lies outside the sparse cone.
+    pub fn outside_cone(&self, path: &Path, is_dir: bool) -> bool {
+        if self.sparse.is_empty() || self.sparse.iter().any(|sparse| path.starts_with(sparse)) {
+            return false;
+        }
+        // Directories leading to a sparse path are walked; files directly inside them are kept.
+        let container = if is_dir {
+            path
+        } else {
+            path.parent().unwrap_or(Path::new(""))
+        };
+        !self
+            .sparse
+            .iter()
+            .any(|sparse| sparse.starts_with(container))
+    }
+
+    /// Whether the directory `path` is only partly inside the sparse cone.
+    ///
+    /// Such directories lead to a sparse path and must be walked entry by entry instead of
+    /// being cloned whole.
+    pub fn partially_included(&self, path: &Path) -> bool {
+        self.sparse
+            .iter()
+            .any(|sparse| sparse != path && sparse.starts_with(path))
+    }
+
+    /// Compile these patterns plus `extra` relative to `root`, keeping the sparse cone.
+    pub fn with_patterns(&self, root: &Path, extra: &[String]) -> Result<Self> {
+        let mut patterns = self.patterns.clone();
+        patterns.extend_from_slice(extra);
+        let mut set = Self::new(root, &patterns)?;
+        set.sparse = self.sparse.clone();
+        Ok(set)
+    }
+
+    /// Rebase the patterns and sparse cone onto the directory `entry`, rooted at `root`.
+    ///
+    /// Used for nested repositories such as submodules, whose paths are relative to their own
+    /// root rather than the repository's.
+    pub fn within(&self, root: &Path, entry: &str) -> Result<Self> {
+        let mut set = Self::new(root, &self.entry_globs(entry))?;
+        let entry = Path::new(entry);
+        if !self.sparse.iter().any(|sparse| entry.starts_with(sparse)) {
+            set.sparse = self
+                .sparse
+                .iter()
+                .filter_map(|sparse| sparse.strip_prefix(entry).ok())
+                .map(Path::to_path_buf)
+                .collect();
+        }
+        Ok(set)
+    }
+
+    /// Whether the repository-relative `path`, or any of its parents, is excluded.
+    pub fn excludes_path(&self, path: &Path, is_dir: bool) -> bool {
+        self.outside_cone(path, is_dir)
+            || self
+                .matcher
+                .matched_path_or_any_parents(path, is_dir)
+                .is_ignore()
+    }
+
+    /// Rebase every pattern that can match below `entry` so it is relative to that directory.
+    fn entry_globs(&self, entry: &str) -> Vec<String> {
+        self.patterns
+            .iter()
+            .filter_map(|pattern| {
+                entry
+                    .split('/')
+                    .filter(|segment| !segment.is_empty())
+                    .try_fold(pattern.clone(), |pattern, segment| {
+                        rebase_pattern(&pattern, segment)
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Rebase a gitignore-style `pattern` onto the top-level directory `entry`.
+///
+/// Returns `None` when the pattern cannot match anything below `entry`.
+fn rebase_pattern(pattern: &str, entry: &str) -> Option<String> {
+    let (body, dir_suffix) = match pattern.strip_suffix('/') {
+        Some(body) => (body, "/"),
+        None => (pattern, ""),
+    };
+
+    // Patterns without a separator match at any depth, so they apply unchanged.
+    if !body.contains('/') {
+        return Some(pattern.to_string());
+    }
+
+    let anchored = body.strip_prefix('/').unwrap_or(body);
+    let (first, rest) = anchored.split_once('/')?;
+    if first == "**" {
+        return Some(format!("{anchored}{dir_suffix}"));
+    }
+    if rest.is_empty() || !segment_matches(first, entry) {
+        return None;
+    }
+
+    Some(format!("/{rest}{dir_suffix}"))
+}
+
+/// Check whether a single glob path segment matches a top-level directory name.
+fn segment_matches(segment: &str, entry: &str) -> bool {
+    let mut builder = GitignoreBuilder::new("");
+    if builder.add_line(None, &format!("/{segment}")).is_err() {
+        return false;
+    }
+    builder
+        .build()
+        .map(|matcher| matcher.matched(entry, true).is_ignore())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn top_level_entries_match_globs() {
+        let set = ExcludeSet::new(
+            Path::new("/repo"),
+            &patterns(&["*.log", "target/", "/node_modules", "crates/*/target"]),
+        )
+        .unwrap();
+
+        assert!(set.excludes_path(Path::new("debug.log"), false));
+        assert!(set.excludes_path(Path::new("target"), true));
+        assert!(!set.excludes_path(Path::new("target"), false));
+        assert!(set.excludes_path(Path::new("node_modules"), true));
+        assert!(!set.excludes_path(Path::new("crates"), true));
+        assert!(!set.excludes_path(Path::new("src"), true));
+    }
+
+    #[test]
+    fn patterns_are_rebased_onto_entries() {
+        assert_eq!(rebase_pattern("*.log", "src").as_deref(), Some("*.log"));
+        assert_eq!(rebase_pattern("target/", "src").as_deref(), Some("target/"));
+        assert_eq!(
+            rebase_pattern("**/node_modules", "web").as_deref(),
+            Some("**/node_modules")
+        );
+        assert_eq!(
+            rebase_pattern("/**/node_modules", "web").as_deref(),
+            Some("**/node_modules")
+        );
+        assert_eq!(
+            rebase_pattern("crates/*/target", "crates").as_deref(),
+            Some("/*/target")
+        );
+        assert_eq!(
+            rebase_pattern("c*/foo/", "crates").as_deref(),
+            Some("/foo/")
+        );
+        assert_eq!(rebase_pattern("crates/*/target", "docs"), None);
+        assert_eq!(rebase_pattern("/node_modules", "web"), None);
+    }
+
+    #[test]
+    fn patterns_are_rebased_onto_nested_directories() {
+        let set = ExcludeSet::new(
+            Path::new("/repo"),
+            &patterns(&[
+                "*.log",
+                "crates/*/target",
+                "/crates/core/tmp/",
+                "docs/build",
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            set.entry_globs("crates/core"),
+            patterns(&["*.log", "/target", "/tmp/"])
+        );
+        assert!(set.excludes_path(Path::new("crates/core/target/debug"), true));
+        assert!(!set.excludes_path(Path::new("crates/core/src"), true));
+    }
+
+    #[test]
+    fn sparse_paths_follow_cone_rules() {
+        let set = ExcludeSet::new(Path::new("/repo"), &patterns(&["*.log"]))
+            .unwrap()
+            .with_sparse_paths(&patterns(&["./crates/foo/", "crates/foo/src", "docs"]))
+            .unwrap();
+
+        assert_eq!(
+            set.sparse_paths(),
+            &[PathBuf::from("crates/foo"), PathBuf::from("docs")]
+        );
+        assert!(!set.excludes_path(Path::new("README.md"), false));
+        assert!(!set.excludes_path(Path::new("crates/Cargo.toml"), false));
+        assert!(!set.excludes_path(Path::new("crates/foo/src/lib.rs"), false));
+        assert!(set.excludes_path(Path::new("crates/foo/debug.log"), false));
+        assert!(set.excludes_path(Path::new("crates/bar"), true));
+        assert!(set.excludes_path(Path::new("crates/bar/lib.rs"), false));
+        assert!(set.excludes_path(Path::new("target"), true));
+        assert!(set.partially_included(Path::new("crates")));
+        assert!(!set.partially_included(Path::new("crates/foo")));
+
+        let nested = set.within(Path::new("/repo/crates"), "crates").unwrap();
+        assert_eq!(nested.sparse_paths(), &[PathBuf::from("foo")]);
+
+        for invalid in ["../outside", "/abs", "."] {
+            let result = ExcludeSet::new(Path::new("/repo"), &[])
+                .unwrap()
+                .with_sparse_paths(&patterns(&[invalid]));
+            assert!(result.is_err(), "{invalid} should be rejected");
+        }
+    }
+
+    #[test]
+    fn negated_patterns_are_rejected() {
+        let result = ExcludeSet::new(Path::new("/repo"), &patterns(&["!keep"]));
+        assert!(matches!(result, Err(GodoError::OperationError(_))));
+    }
+}
@@ -1,7 +1,9 @@
 use std::{
-    env,
+    collections::HashSet,
+    env, fs,
+    io::Write,
     path::{Path, PathBuf},
-    process::{Command, Output},
+    process::{self, Command, Output, Stdio},
 };
 
 use anyhow::{Context, Result};
@@ -24,6 +26,77 @@ fn run_git(repo_path: &Path, args: &[&str]) -> Result<Output> {
     Ok(output)
 }
 
+/// Run a git command against the alternate index file `index`, optionally feeding `input` to
+/// its stdin. The worktree's own index is left untouched.
+fn run_git_with_index(
+    repo_path: &Path,
+    index: &Path,
+    args: &[&str],
+    input: Option<&[u8]>,
+) -> Result<Output> {
+    let command = format!("git {}", args.join(" "));
+    let mut child = Command::new("git")
+        .current_dir(repo_path)
+        .env("GIT_INDEX_FILE", index)
+        .args(args)
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to execute git command: {command}"))?;
+
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        stdin.write_all(input)?;
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Git command failed: {}\nError: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output)
+}
+
+/// Absolute path of `name` inside the worktree's administrative directory.
+fn git_path(repo_path: &Path, name: &str) -> Result<PathBuf> {
+    let output = run_git(
+        repo_path,
+        &["rev-parse", "--path-format=absolute", "--git-path", name],
+    )?;
+    Ok(PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    ))
+}
+
+/// Path of a scratch index file for `purpose`, private to this process, inside the worktree's
+/// administrative directory.
+fn scratch_index(repo_path: &Path, purpose: &str) -> Result<PathBuf> {
+    git_path(
+        repo_path,
+        &format!("godo-{purpose}-index-{}", process::id()),
+    )
+}
+
+/// Copy the worktree's index into a scratch index for `purpose`, returning the copy's path.
+///
+/// Git commands run against the copy never take the real index's lock, so they cannot collide
+/// with Git commands the user runs at the same time.
+fn copy_index(repo_path: &Path, purpose: &str) -> Result<PathBuf> {
+    let index = scratch_index(repo_path, purpose)?;
+    let real_index = git_path(repo_path, "index")?;
+    if real_index.exists() {
+        fs::copy(&real_index, &index)?;
+    }
+    Ok(index)
+}
+
 /// Resolve a rev to its full commit hash, failing if the revision is invalid.
 pub fn rev_parse(repo_path: &Path, rev: &str) -> Result<String> {
     let commit_ref = format!("{rev}^{{commit}}");
@@ -31,6 +104,18 @@ pub fn rev_parse(repo_path: &Path, rev: &str) -> Result<String> {
     Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
+/// Whether `HEAD` points to a commit, which it does not in a repository without commits.
+pub fn has_head_commit(repo_path: &Path) -> bool {
+    rev_parse(repo_path, "HEAD").is_ok()
+}
+
+/// Resolve a rev to the hash of its tree.
+pub fn tree_id(repo_path: &Path, rev: &str) -> Result<String> {
+    let tree_ref = format!("{rev}^{{tree}}");
+    let output = run_git(repo_path, &["rev-parse", "--verify", &tree_ref])?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
 /// Resolve the merge-base between two revisions.
 pub fn merge_base(repo_path: &Path, left: &str, right: &str) -> Result<String> {
     let output = run_git(repo_path, &["merge-base", left, right])?;
@@ -77,7 +162,8 @@ pub fn has_uncommitted_changes(repo_path: &Path) -> Result<bool> {
     Ok(!status_output.trim().is_empty())
 }
 
-/// List untracked files, respecting standard Git ignore rules.
+/// List untracked files, respecting standard Git ignore rules, including those inside
+/// populated submodules.
 pub fn untracked_files(repo_path: &Path) -> Result<Vec<PathBuf>> {
     let output = run_git(
         repo_path,
@@ -93,9 +179,39 @@ pub fn untracked_files(repo_path: &Path) -> Result<Vec<PathBuf>> {
         files.push(PathBuf::from(path));
     }
 
+    // Git does not descend into submodules, so list their untracked files separately.
+    for submodule in populated_submodules(repo_path)? {
+        for path in untracked_files(&repo_path.join(&submodule.path))? {
+            files.push(submodule.path.join(path));
+        }
+    }
+
     Ok(files)
 }
 
+/// List ignored, untracked paths. Wholly ignored directories are reported once with a
+/// trailing `/`.
+pub fn ignored_paths(repo_path: &Path) -> Result<Vec<PathBuf>> {
+    let output = run_git(
+        repo_path,
+        &[
+            "ls-files",
+            "--others",
+            "--ignored",
+            "--exclude-standard",
+            "--directory",
+            "-z",
+        ],
+    )?;
+
+    Ok(output
+        .stdout
+        .split(|byte| *byte == 0)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| PathBuf::from(String::from_utf8_lossy(entry).to_string()))
+        .collect())
+}
+
 /// Statistics about uncommitted changes.
 #[derive(Debug, Clone, Copy, Default)]
 pub struct DiffStats {
@@ -106,14 +222,35 @@ pub struct DiffStats {
 }
 
 /// Get diff statistics for uncommitted changes (both staged and unstaged).
+///
+/// Changes inside populated submodules are counted against the commit the superproject
+/// records for them.
 pub fn diff_stats(repo_path: &Path) -> Result<DiffStats> {
+    diff_stats_against(repo_path, "HEAD")
+}
+
+/// Get diff statistics for the working tree at `repo_path` relative to `base`.
+fn diff_stats_against(repo_path: &Path, base: &str) -> Result<DiffStats> {
+    let submodules = populated_submodules(repo_path)?;
+    let submodule_paths: HashSet<String> = submodules
+        .iter()
+        .map(|submodule| submodule.path.to_string_lossy().into_owned())
+        .collect();
+
     // Use --numstat to get machine-readable output: "added\tremoved\tfilename"
-    let output = run_git(repo_path, &["diff", "HEAD", "--numstat"])?;
+    let output = run_git(repo_path, &["diff", base, "--numstat"])?;
     let output_str = String::from_utf8_lossy(&output.stdout);
 
     let mut stats = DiffStats::default();
     for line in output_str.lines() {
         let parts: Vec<&str> = line.split('\t').collect();
+        // Submodule rows only count the commit pointer; their contents are diffed below.
+        if parts
+            .get(2)
+            .is_some_and(|path| submodule_paths.contains(*path))
+        {
+            continue;
+        }
         if parts.len() >= 2 {
             // Binary files show "-" instead of numbers
             if let Ok(ins) = parts[0].parse::<usize>() {
@@ -125,16 +262,182 @@ pub fn diff_stats(repo_path: &Path) -> Result<DiffStats> {
         }
     }
 
+    for submodule in submodules {
+        let inner = diff_stats_against(&repo_path.join(&submodule.path), &submodule.commit)?;
+        stats.insertions += inner.insertions;
+        stats.deletions += inner.deletions;
+    }
+
     Ok(stats)
 }
+
+/// A submodule recorded in a repository's `HEAD` commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Submodule {
+    /// Path of the submodule relative to the repository root.
+    pub path: PathBuf,
+    /// Commit the superproject records for the submodule.
+    pub commit: String,
+}
+
+/// List the submodules recorded in `HEAD` of the repository at `repo_path`.
+pub fn submodules(repo_path: &Path) -> Result<Vec<Submodule>> {
+    // Skip listing the whole tree for the common case of a repository without submodules.
+    if !repo_path.join(".gitmodules").exists() {
+        return Ok(Vec::new());
+    }
+    let output = run_git(repo_path, &["ls-tree", "-r", "-z", "HEAD"])?;
+    Ok(output
+        .stdout
+        .split(|byte| *byte == 0)
+        .filter_map(|entry| {
+            // Entries look like "<mode> <type> <object>\t<path>".
+            let entry = String::from_utf8_lossy(entry);
+            let (info, path) = entry.split_once('\t')?;
+            let mut fields = info.split(' ');
+            let (_, kind, commit) = (fields.next()?, fields.next()?, fields.next()?);
+            (kind == "commit").then(|| Submodule {
+                path: PathBuf::from(path),
+                commit: commit.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// List the submodules of the repository at `repo_path` that have a working tree.
+pub fn populated_submodules(repo_path: &Path) -> Result<Vec<Submodule>> {
+    Ok(submodules(repo_path)?
+        .into_iter()
+        .filter(|submodule| repo_path.join(&submodule.path).join(".git").exists())
+        .collect())
+}
+
+/// Add a worktree with a detached `HEAD` at `commit`.
+///
+/// Used for branchless sandboxes, and to give each submodule of a sandbox its own worktree of
+/// the source submodule's repository. With `checkout` false, the index and working tree are
+/// left empty.
+pub fn create_detached_worktree(
+    repo_path: &Path,
+    worktree_path: &Path,
+    commit: &str,
+    checkout: bool,
+) -> Result<()> {
+    let worktree_path_str = worktree_path
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("Invalid worktree path"))?;
+
+    let mut args = vec!["worktree", "add", "--quiet", "--detach"];
+    if !checkout {
+        args.push("--no-checkout");
+    }
+    args.extend([worktree_path_str, commit]);
+    run_git(repo_path, &args)?;
+    Ok(())
+}
+
+/// Restrict the worktree at `repo_path` to `paths` with a cone-mode sparse checkout.
+///
+/// Index entries outside the cone are marked skip-worktree and their files removed; files
+/// already present inside the cone are left alone.
+pub fn sparse_checkout_set(repo_path: &Path, paths: &[PathBuf]) -> Result<()> {
+    let mut args = vec![
+        "sparse-checkout".to_string(),
+        "set".to_string(),
+        "--cone".to_string(),
+    ];
+    args.extend(paths.iter().map(|path| path.to_string_lossy().into_owned()));
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    run_git(repo_path, &args)?;
+    Ok(())
+}
+
+/// List the cone of a sparse worktree, or nothing when the worktree is not sparse.
+pub fn sparse_checkout_paths(repo_path: &Path) -> Result<Vec<PathBuf>> {
+    let enabled = Command::new("git")
+        .current_dir(repo_path)
+        .args(["config", "--bool", "core.sparseCheckout"])
+        .output()
+        .with_context(|| "Failed to execute git config")?;
+    if String::from_utf8_lossy(&enabled.stdout).trim() != "true" {
+        return Ok(Vec::new());
+    }
+    let output = run_git(repo_path, &["sparse-checkout", "list"])?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Fill the index and working tree of an unchecked-out worktree from `HEAD`.
+///
+/// Unlike `git worktree add` with a checkout, this honours a sparse checkout configured after
+/// the worktree was added.
+pub fn checkout_head(repo_path: &Path) -> Result<()> {
+    run_git(repo_path, &["read-tree", "-m", "-u", "HEAD"])?;
+    Ok(())
+}
+
+/// Delete the working trees of populated submodules below `worktree_path`.
+///
+/// Git refuses to remove a worktree that contains submodules. Each submodule's directory is
+/// deleted, nested submodules first, and the repository it belongs to forgets the worktree.
+fn remove_submodule_worktrees(worktree_path: &Path) -> Result<()> {
+    for submodule in populated_submodules(worktree_path)? {
+        let path = worktree_path.join(&submodule.path);
+        remove_submodule_worktrees(&path)?;
+        let common_dir = run_git(&path, &["rev-parse", "--git-common-dir"])?;
+        let common_dir = path.join(String::from_utf8_lossy(&common_dir.stdout).trim());
+        fs::remove_dir_all(&path)?;
+        run_git(&common_dir, &["worktree", "prune"])?;
+    }
+    Ok(())
+}
+
+/// Prune administrative data for worktrees whose directories no longer exist.
+pub fn prune_worktrees(repo_path: &Path) -> Result<()> {
+    run_git(repo_path, &["worktree", "prune"])?;
+    Ok(())
+}
+
+/// Repair the administrative links of worktrees that were moved to `paths`.
+pub fn repair_worktrees(repo_path: &Path, paths: &[PathBuf]) -> Result<()> {
+    let mut args = vec!["worktree".to_string(), "repair".to_string()];
+    args.extend(paths.iter().map(|path| path.to_string_lossy().into_owned()));
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    run_git(repo_path, &args)?;
+    Ok(())
+}
+
+/// Read the administrative directory that the linked worktree at `worktree_path` points to.
+///
+/// Linked worktrees have a `.git` file of the form `gitdir: <path>` instead of a directory.
+/// Returns `None` when there is no such file.
+pub fn worktree_gitdir(worktree_path: &Path) -> Option<PathBuf> {
+    let contents = fs::read_to_string(worktree_path.join(".git")).ok()?;
+    let gitdir = contents.strip_prefix("gitdir:")?.trim();
+    Some(worktree_path.join(gitdir))
+}
+
 /// Determine if a branch named `branch_name` exists in the repository.
 pub fn has_branch(repo_path: &Path, branch_name: &str) -> Result<bool> {
     let output = run_git(repo_path, &["branch", "--list", branch_name])?;
     let branch_output = String::from_utf8_lossy(&output.stdout);
     Ok(!branch_output.trim().is_empty())
 }
-/// Create a new worktree for `branch_name` under `worktree_path`.
-pub fn create_worktree(repo_path: &Path, worktree_path: &Path, branch_name: &str) -> Result<()> {
+
+/// Create a new worktree on a new branch starting at `start_point`.
+///
+/// Without a start point, Git starts the branch at `HEAD`, or creates it unborn when `HEAD` has
+/// no commits yet. With `checkout` false, only the worktree's `.git` file is written: the index
+/// and working tree are left empty for the caller to populate.
+pub fn create_worktree_at(
+    repo_path: &Path,
+    worktree_path: &Path,
+    branch_name: &str,
+    start_point: Option<&str>,
+    checkout: bool,
+) -> Result<()> {
     if has_branch(repo_path, branch_name)? {
         anyhow::bail!("Branch '{}' already exists", branch_name);
     }
@@ -143,18 +446,186 @@ pub fn create_worktree(repo_path: &Path, worktree_path: &Path, branch_name: &str
         .to_str()
         .ok_or_else(|| anyhow::anyhow!("Invalid worktree path"))?;
 
-    run_git(
+    let mut args = vec!["worktree", "add", "--quiet"];
+    if !checkout {
+        args.push("--no-checkout");
+    }
+    args.extend(["-b", branch_name, worktree_path_str]);
+    args.extend(start_point);
+    run_git(repo_path, &args)?;
+
+    Ok(())
+}
+
+/// Write the index of the worktree at `repo_path` as a tree object and return its hash.
+///
+/// Returns `None` when the index has unresolved merge conflicts, which cannot be represented as
+/// a tree.
+pub fn index_tree(repo_path: &Path) -> Result<Option<String>> {
+    let unmerged = run_git(repo_path, &["ls-files", "--unmerged"])?;
+    if !unmerged.stdout.is_empty() {
+        return Ok(None);
+    }
+    let index = copy_index(repo_path, "tree")?;
+    let result = run_git_with_index(repo_path, &index, &["write-tree"], None);
+    if index.exists() {
+        fs::remove_file(&index)?;
+    }
+    Ok(Some(
+        String::from_utf8_lossy(&result?.stdout).trim().to_string(),
+    ))
+}
+
+/// Record the complete working tree at `repo_path`, untracked files included, as a tree object.
+///
+/// Ignored files are left out. The worktree's index is not modified: the snapshot is staged in a
+/// scratch index seeded from it, which also lets Git skip rehashing unchanged files.
+pub fn snapshot_tree(repo_path: &Path) -> Result<String> {
+    let index = copy_index(repo_path, "snapshot")?;
+    let result = run_git_with_index(repo_path, &index, &["add", "--all"], None)
+        .and_then(|_| run_git_with_index(repo_path, &index, &["write-tree"], None));
+    if index.exists() {
+        fs::remove_file(&index)?;
+    }
+    Ok(String::from_utf8_lossy(&result?.stdout).trim().to_string())
+}
+
+/// Create a commit object for `tree` with the single parent `parent`, returning its hash.
+///
+/// No ref is updated.
+pub fn commit_tree(repo_path: &Path, tree: &str, parent: &str, message: &str) -> Result<String> {
+    let output = run_git(
+        repo_path,
+        &["commit-tree", tree, "-p", parent, "-m", message],
+    )?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Point the ref `name` at `commit`, creating it if needed.
+pub fn update_ref(repo_path: &Path, name: &str, commit: &str) -> Result<()> {
+    run_git(repo_path, &["update-ref", name, commit])?;
+    Ok(())
+}
+
+/// Delete the ref `name`. Deleting a ref that does not exist succeeds.
+pub fn delete_ref(repo_path: &Path, name: &str) -> Result<()> {
+    run_git(repo_path, &["update-ref", "-d", name])?;
+    Ok(())
+}
+
+/// Commit the changes between the trees `from` and `to` on top of `HEAD`.
+///
+/// The changes are merged into `HEAD` in a scratch index, three-way so that changes already
+/// committed are not applied twice, and committed from there. The working tree is untouched.
+/// Afterwards only the index entries of the committed paths are reset to the new `HEAD`, so
+/// anything staged elsewhere stays staged.
+pub fn commit_tree_changes(repo_path: &Path, from: &str, to: &str, message: &str) -> Result<()> {
+    let patch = run_git(repo_path, &["diff", "--binary", "--no-renames", from, to])?.stdout;
+    if patch.is_empty() {
+        anyhow::bail!("Nothing to commit: no changes since {from}");
+    }
+    let paths = run_git(
+        repo_path,
+        &["diff", "--name-only", "-z", "--no-renames", from, to],
+    )?
+    .stdout;
+
+    let index = scratch_index(repo_path, "commit")?;
+    let result = run_git_with_index(repo_path, &index, &["read-tree", "HEAD"], None)
+        .and_then(|_| {
+            run_git_with_index(
+                repo_path,
+                &index,
+                &["apply", "--cached", "--3way"],
+                Some(&patch),
+            )
+        })
+        .and_then(|_| run_git_with_index(repo_path, &index, &["commit", "-m", message], None));
+    if index.exists() {
+        fs::remove_file(&index)?;
+    }
+    result?;
+
+    run_git_with_index(
         repo_path,
+        &git_path(repo_path, "index")?,
         &[
-            "worktree",
-            "add",
+            "--literal-pathspecs",
+            "reset",
             "--quiet",
-            "-b",
-            branch_name,
-            worktree_path_str,
+            "HEAD",
+            "--pathspec-from-file=-",
+            "--pathspec-file-nul",
         ],
+        Some(&paths),
     )?;
+    Ok(())
+}
+
+/// Replace the index of the worktree at `repo_path` with the contents of `tree_ish`.
+pub fn read_tree(repo_path: &Path, tree_ish: &str) -> Result<()> {
+    run_git(repo_path, &["read-tree", tree_ish])?;
+    Ok(())
+}
+
+/// Refresh cached stat information in the index after files were written behind Git's back.
+pub fn refresh_index(repo_path: &Path) -> Result<()> {
+    let output = Command::new("git")
+        .current_dir(repo_path)
+        .args(["update-index", "-q", "--refresh"])
+        .output()
+        .with_context(|| "Failed to execute git update-index")?;
+
+    // Exit status 1 only reports that some files differ from the index, which is expected.
+    match output.status.code() {
+        Some(0 | 1) => Ok(()),
+        _ => anyhow::bail!(
+            "Git command failed: git update-index -q --refresh\nError: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+    }
+}
+
+/// List the paths of all files tracked in the index.
+pub fn tracked_files(repo_path: &Path) -> Result<Vec<PathBuf>> {
+    let output = run_git(repo_path, &["ls-files", "-z"])?;
+    Ok(output
+        .stdout
+        .split(|byte| *byte == 0)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| PathBuf::from(String::from_utf8_lossy(entry).to_string()))
+        .collect())
+}
 
+/// Write the indexed version of `paths` into the working tree, overwriting existing files.
+pub fn checkout_index(repo_path: &Path, paths: &[PathBuf]) -> Result<()> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+
+    let mut child = Command::new("git")
+        .current_dir(repo_path)
+        .args(["checkout-index", "--force", "-z", "--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| "Failed to execute git checkout-index")?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        for path in paths {
+            stdin.write_all(path.to_string_lossy().as_bytes())?;
+            stdin.write_all(b"\0")?;
+        }
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Git command failed: git checkout-index --stdin\nError: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
     Ok(())
 }
 /// Remove the worktree located at `worktree_path`, optionally forcing removal.
@@ -169,6 +640,19 @@ pub fn remove_worktree(repo_path: &Path, worktree_path: &Path, force: bool) -> R
         return Ok(());
     }
 
+    let has_submodules = worktree_path.exists()
+        && populated_submodules(worktree_path).is_ok_and(|submodules| !submodules.is_empty());
+    if has_submodules {
+        // Submodules are removed by hand, so check for changes before anything is deleted.
+        if !force && has_uncommitted_changes(worktree_path)? {
+            anyhow::bail!(
+                "Worktree {} has uncommitted changes",
+                worktree_path.display()
+            );
+        }
+        remove_submodule_worktrees(worktree_path)?;
+    }
+
     let worktree_path_str = worktree_path
         .to_str()
         .ok_or_else(|| anyhow::anyhow!("Invalid worktree path"))?;
@@ -526,18 +1010,6 @@ pub fn default_integration_target(repo_path: &Path) -> Result<Option<String>> {
     Ok(None)
 }
 
-/// Reset the working directory to match `HEAD`, removing all uncommitted changes.
-pub fn reset_hard(repo_path: &Path) -> Result<()> {
-    run_git(repo_path, &["reset", "--hard", "HEAD"])?;
-    Ok(())
-}
-
-/// Remove untracked files and directories from the working tree.
-pub fn clean(repo_path: &Path) -> Result<()> {
-    run_git(repo_path, &["clean", "-fd"])?;
-    Ok(())
-}
-
 #[cfg(test)]
 mod tests {
     use std::fs;
@@ -641,7 +1113,7 @@ mod tests {
 
         // Create a worktree
         let worktree_path = repo_path.parent().unwrap().join("test-worktree");
-        create_worktree(&repo_path, &worktree_path, "test-branch")?;
+        create_worktree_at(&repo_path, &worktree_path, "test-branch", None, true)?;
 
         // Verify worktree was created
         assert!(worktree_path.exists());
@@ -695,11 +1167,12 @@ mod tests {
 
         // Create first worktree using temp_dir as the base
         let worktree_path1 = temp_dir.path().join("test-worktree-1");
-        create_worktree(&repo_path, &worktree_path1, "duplicate-branch")?;
+        create_worktree_at(&repo_path, &worktree_path1, "duplicate-branch", None, true)?;
 
         // Try to create second worktree with same branch name but different path
         let worktree_path2 = temp_dir.path().join("test-worktree-2");
-        let result = create_worktree(&repo_path, &worktree_path2, "duplicate-branch");
+        let result =
+            create_worktree_at(&repo_path, &worktree_path2, "duplicate-branch", None, true);
 
         // Should fail because branch already exists
         assert!(result.is_err());
@@ -729,7 +1202,7 @@ mod tests {
 
         // Create a worktree
         let worktree_path = temp_dir.path().join("test-worktree");
-        create_worktree(&repo_path, &worktree_path, "test-branch")?;
+        create_worktree_at(&repo_path, &worktree_path, "test-branch", None, true)?;
 
         // Verify worktree exists
         assert!(worktree_path.exists());
@@ -754,7 +1227,7 @@ mod tests {
 
         // Create a worktree
         let worktree_path = temp_dir.path().join("test-worktree");
-        create_worktree(&repo_path, &worktree_path, "test-branch")?;
+        create_worktree_at(&repo_path, &worktree_path, "test-branch", None, true)?;
 
         // Add uncommitted changes
         fs::write(worktree_path.join("uncommitted.txt"), "uncommitted content")?;
@@ -935,45 +1408,6 @@ mod tests {
         Ok(())
     }
 
-    #[test]
-    fn test_reset_hard_and_clean() -> Result<()> {
-        let (_temp_dir, repo_path) = setup_test_repo()?;
-
-        // Create and commit a file
-        fs::write(repo_path.join("test.txt"), "initial content")?;
-        run_git(&repo_path, &["add", "test.txt"])?;
-        run_git(&repo_path, &["commit", "-m", "Initial commit"])?;
-
-        // Modify the file and create a new untracked file
-        fs::write(repo_path.join("test.txt"), "modified content")?;
-        fs::write(repo_path.join("untracked.txt"), "untracked content")?;
-
-        // Should have uncommitted changes
-        assert!(has_uncommitted_changes(&repo_path)?);
-
-        // Reset to HEAD
-        reset_hard(&repo_path)?;
-
-        // Modified file should be back to original state
-        let content = fs::read_to_string(repo_path.join("test.txt"))?;
-        assert_eq!(content, "initial content");
-
-        // Untracked file should still exist
-        assert!(repo_path.join("untracked.txt").exists());
-
-        // Should still have uncommitted changes (untracked file)
-        assert!(has_uncommitted_changes(&repo_path)?);
-
-        // Clean untracked files
-        clean(&repo_path)?;
-
-        // Should have no uncommitted changes after cleaning
-        assert!(!has_uncommitted_changes(&repo_path)?);
-        assert!(!repo_path.join("untracked.txt").exists());
-
-        Ok(())
-    }
-
     #[test]
     fn test_branch_merge_status_detects_diverged_and_clean() -> Result<()> {
         let (_temp_dir, repo_path) = setup_test_repo()?;
@@ -1,7 +1,3 @@
-#[cfg(unix)]
-use std::os::unix::fs::symlink;
-#[cfg(windows)]
-use std::os::windows::fs::{symlink_dir, symlink_file};
 use std::{
     collections::HashSet,
     env, fs,
@@ -9,14 +5,21 @@ use std::{
     time::{SystemTime, UNIX_EPOCH},
 };
 
-use clonetree::{Options, clone_tree};
+use sysinfo::System;
 
 use crate::{
+    clone::{self, CloneJob},
     error::{GodoError, Result},
+    excludes::ExcludeSet,
     git::{self, MergeStatus},
-    session::{LEASE_DIR_NAME, SessionManager},
+    hooks,
+    naming::{self, BranchNamespace},
+    registry::{self, ProjectEntry, ProjectRegistry},
+    rollback::CreationRollback,
+    session::{LEASE_DIR_NAME, LockedSandbox, SessionManager},
     store::SandboxMetadataStore,
     types::*,
+    usage,
 };
 
 /// Hardcoded fallback targets when dynamic detection fails.
@@ -27,6 +30,62 @@ fn git_error(error: &anyhow::Error) -> GodoError {
     GodoError::GitError(error.to_string())
 }
 
+/// Find the sandbox worktrees inside `project_dir`, as paths relative to it.
+///
+/// Namespace directories are searched recursively; godo's bookkeeping directories are skipped.
+fn sandbox_dirs(project_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        let Ok(entries) = fs::read_dir(project_dir.join(&relative)) else {
+            continue;
+        };
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name();
+            let internal = name == Godo::LEASE_DIR || name == Godo::METADATA_DIR;
+            if !entry.file_type()?.is_dir() || (relative.as_os_str().is_empty() && internal) {
+                continue;
+            }
+            if entry.path().join(".git").exists() {
+                found.push(relative.join(name));
+            } else {
+                pending.push(relative.join(name));
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Remove a project directory that holds nothing but godo's bookkeeping, returning whether it
+/// is gone.
+///
+/// Leases are only meaningful next to their sandboxes, and an empty metadata directory records
+/// nothing, so neither keeps the directory alive.
+fn remove_if_only_bookkeeping(dir: &Path) -> Result<bool> {
+    if !dir.exists() {
+        return Ok(true);
+    }
+    let leftovers = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .any(|entry| {
+            let name = entry.file_name();
+            let empty = || fs::read_dir(entry.path()).is_ok_and(|mut dir| dir.next().is_none());
+            name != Godo::LEASE_DIR && !(name == Godo::METADATA_DIR && empty())
+        });
+    if leftovers {
+        return Ok(false);
+    }
+    fs::remove_dir_all(dir)?;
+    Ok(true)
+}
+
+/// Map project registry errors into a `GodoError::OperationError`.
+fn registry_error(error: &anyhow::Error) -> GodoError {
+    GodoError::OperationError(format!("Project registry error: {error}"))
+}
+
 /// Outcome of resolving a sandbox base commit.
 struct BaseResolution {
     /// Resolved commit hash.
@@ -37,23 +96,20 @@ struct BaseResolution {
     fallback_target: Option<String>,
 }
 
-/// Validates that a sandbox name contains only allowed characters (a-zA-Z0-9-_)
+/// Validates that a sandbox name is one or more `/`-separated components, such as `bug/1234`,
+/// each containing only allowed characters (a-zA-Z0-9-_)
 fn validate_sandbox_name(name: &str) -> Result<()> {
-    if name.is_empty() {
-        return Err(GodoError::SandboxError {
-            name: name.to_string(),
-            message: "names can only contain letters, numbers, hyphens, and underscores"
-                .to_string(),
-        });
-    }
-
-    if !name
-        .chars()
-        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
-    {
+    let valid = name.split('/').all(|component| {
+        !component.is_empty()
+            && component
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    });
+    if !valid {
         return Err(GodoError::SandboxError {
             name: name.to_string(),
-            message: "names can only contain letters, numbers, hyphens, and underscores"
+            message: "names can only contain letters, numbers, hyphens, and underscores, \
+                      optionally grouped into namespaces with single slashes"
                 .to_string(),
         });
     }
@@ -75,6 +131,15 @@ fn clean_name(name: &str) -> String {
         .collect()
 }
 
+/// Convert a path relative to the project directory into a sandbox name such as `bug/1234`.
+fn path_to_name(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
 /// Extract and clean the project name from a Git repository path.
 fn project_name(repo_path: &Path) -> Result<String> {
     let name = repo_path
@@ -103,9 +168,15 @@ fn project_name(repo_path: &Path) -> Result<String> {
     Ok(cleaned)
 }
 
-/// Return the Git branch name for a given sandbox name.
-fn branch_name(sandbox_name: &str) -> String {
-    format!("godo/{}", sandbox_name)
+/// Outcome of creating a new sandbox.
+#[derive(Default)]
+struct CreatedSandbox {
+    /// Whether the sandbox was reset to a clean state after cloning.
+    cleaned: bool,
+    /// Setup hooks that ran, in order.
+    hooks_run: Vec<String>,
+    /// How the sandbox's files were cloned.
+    clone_stats: CloneStats,
 }
 
 /// Manager for creating and operating on ephemeral Git sandboxes based on
@@ -120,6 +191,8 @@ pub struct Godo {
     godo_dir: PathBuf,
     /// Root of the Git repository the sandboxes operate on.
     repo_dir: PathBuf,
+    /// Namespace mapping sandbox names to branch names.
+    branches: BranchNamespace,
 }
 
 impl Godo {
@@ -156,13 +229,354 @@ impl Godo {
         // Canonicalize the repository root to keep sandbox paths stable.
         let repo_dir = fs::canonicalize(&repo_dir).unwrap_or(repo_dir);
 
-        Ok(Self { godo_dir, repo_dir })
+        let godo = Self {
+            godo_dir,
+            repo_dir,
+            branches: BranchNamespace::default(),
+        };
+        Ok(godo)
+    }
+
+    /// Use `prefix` instead of `godo/` for sandbox branch names.
+    ///
+    /// A trailing `/` is added when missing; an empty prefix keeps the default. Fails when the
+    /// prefix would not form valid branch names.
+    pub fn with_branch_prefix(self, prefix: &str) -> Result<Self> {
+        if prefix.trim().is_empty() {
+            return Ok(self);
+        }
+        Ok(self.with_branch_namespace(BranchNamespace::new(prefix)?))
+    }
+
+    /// Use `branches` to name sandbox branches.
+    pub fn with_branch_namespace(mut self, branches: BranchNamespace) -> Self {
+        self.branches = branches;
+        self
+    }
+
+    /// Namespace mapping sandbox names to the branches backing them.
+    pub fn branch_namespace(&self) -> &BranchNamespace {
+        &self.branches
+    }
+
+    /// Root of the Git repository the sandboxes operate on.
+    pub fn repo_dir(&self) -> &Path {
+        &self.repo_dir
+    }
+
+    /// Directory holding the project directories and godo's own files.
+    pub fn godo_dir(&self) -> &Path {
+        &self.godo_dir
+    }
+
+    /// Return the Git branch name backing a sandbox.
+    pub fn branch_name(&self, sandbox_name: &str) -> String {
+        self.branches.branch(sandbox_name)
+    }
+
+    /// Name of the ref holding the baseline commit of a sandbox.
+    fn baseline_ref(&self, sandbox_name: &str) -> String {
+        self.branches.baseline_ref(sandbox_name)
+    }
+
+    /// Identity of the project, such as `app-1f2e3d4c`: the repository's readable name and a
+    /// hash of its path.
+    fn project_id(&self) -> Result<String> {
+        Ok(registry::project_id(
+            &project_name(&self.repo_dir)?,
+            &self.repo_dir,
+        ))
+    }
+
+    /// Directory within the godo directory that holds this repository's sandboxes.
+    pub fn project_dir(&self) -> Result<PathBuf> {
+        Ok(self.godo_dir.join(self.project_id()?))
+    }
+
+    /// Record the project in the godo directory's registry at its current location, unless it
+    /// is recorded that way already.
+    fn register_project(&self) -> Result<()> {
+        let registry = ProjectRegistry::new(&self.godo_dir);
+        let id = self.project_id()?;
+        let entry = ProjectEntry {
+            repo_dir: self.repo_dir.clone(),
+            project_dir: Some(self.project_dir()?),
+        };
+        let registered = registry.projects().map_err(|e| registry_error(&e))?;
+        if registered.get(&id) == Some(&entry) {
+            return Ok(());
+        }
+        registry
+            .update(|projects| {
+                projects.insert(id, entry);
+                Ok(())
+            })
+            .map_err(|e| registry_error(&e))
+    }
+
+    /// List every project registered in the godo directory, ordered by identity.
+    ///
+    /// This covers other repositories too; use [`Godo::for_project`] to work with their
+    /// sandboxes.
+    pub fn projects(&self) -> Result<Vec<ProjectInfo>> {
+        let projects = ProjectRegistry::new(&self.godo_dir)
+            .projects()
+            .map_err(|e| registry_error(&e))?;
+        Ok(projects
+            .into_iter()
+            .map(|(id, entry)| ProjectInfo {
+                project_dir: self.godo_dir.join(&id),
+                is_stale: entry.is_stale(),
+                repo_dir: entry.repo_dir,
+                id,
+            })
+            .collect())
+    }
+
+    /// Create a manager for the sandboxes of another registered project, sharing this
+    /// manager's godo directory and branch namespace.
+    pub fn for_project(&self, project: &ProjectInfo) -> Result<Self> {
+        Ok(
+            Self::new(self.godo_dir.clone(), Some(project.repo_dir.clone()))?
+                .with_branch_namespace(self.branches.clone()),
+        )
+    }
+
+    /// Delete a stale project: its project directory, with every sandbox in it, and its
+    /// registry entry.
+    ///
+    /// Fails without touching anything when the project's repository exists again, since its
+    /// sandboxes are then still usable.
+    pub fn prune_project(&self, project: &ProjectInfo) -> Result<()> {
+        let project_dir = self.godo_dir.join(&project.id);
+        ProjectRegistry::new(&self.godo_dir)
+            .update(|projects| {
+                if let Some(entry) = projects.get(&project.id)
+                    && !entry.is_stale()
+                {
+                    anyhow::bail!(
+                        "project {} is not stale: {} still exists",
+                        project.id,
+                        entry.repo_dir.display()
+                    );
+                }
+                if project_dir.exists() {
+                    fs::remove_dir_all(&project_dir).map_err(|e| {
+                        anyhow::anyhow!("failed to remove {}: {e}", project_dir.display())
+                    })?;
+                }
+                projects.remove(&project.id);
+                Ok(())
+            })
+            .map_err(|e| registry_error(&e))
+    }
+
+    /// Repair this project's sandboxes after the repository or the godo directory was moved.
+    ///
+    /// Either move breaks the links between the repository and its worktrees, so sandboxes
+    /// show up as dangling. A moved repository also gets a new project identity, leaving its
+    /// sandboxes behind in the old project directory; that directory is recognised because its
+    /// worktrees belong to this repository, and its sandboxes move over with their metadata.
+    /// Every sandbox then gets the equivalent of `git worktree repair`, and the project is
+    /// recorded at its new location. Sandboxes still in the project directory of an older godo
+    /// are moved over too.
+    ///
+    /// Nothing is repaired implicitly; frontends call this when [`Godo::needs_repair`] says so,
+    /// before changing any sandboxes.
+    pub fn repair(&self) -> Result<RepairReport> {
+        let registry = ProjectRegistry::new(&self.godo_dir);
+        let id = self.project_id()?;
+        let project_dir = self.project_dir()?;
+        let projects = registry.projects().map_err(|e| registry_error(&e))?;
+
+        let mut report = RepairReport {
+            adopted: self.migrate_legacy_project()?,
+            ..RepairReport::default()
+        };
+        for (old_id, entry) in &projects {
+            let old_dir = self.godo_dir.join(old_id);
+            if *old_id == id || !entry.is_stale() || !self.is_previous_location(entry, &old_dir)? {
+                continue;
+            }
+            report
+                .adopted
+                .extend(self.adopt_sandboxes(&old_dir, &sandbox_dirs(&old_dir)?)?);
+            if remove_if_only_bookkeeping(&old_dir)? {
+                registry
+                    .update(|projects| {
+                        projects.remove(old_id);
+                        Ok(())
+                    })
+                    .map_err(|e| registry_error(&e))?;
+            }
+            report.previous_projects.push(old_id.clone());
+        }
+
+        let sandboxes = sandbox_dirs(&project_dir)?;
+        if !sandboxes.is_empty() {
+            let paths: Vec<PathBuf> = sandboxes.iter().map(|dir| project_dir.join(dir)).collect();
+            git::repair_worktrees(&self.repo_dir, &paths).map_err(|e| git_error(&e))?;
+            report.repaired = sandboxes.iter().map(|dir| path_to_name(dir)).collect();
+        }
+        if !sandboxes.is_empty() || projects.contains_key(&id) {
+            self.register_project()?;
+        }
+        Ok(report)
+    }
+
+    /// Whether [`Godo::repair`] has work to do, because the repository or the godo directory
+    /// moved or sandboxes are still in the project directory of an older godo.
+    pub fn needs_repair(&self) -> Result<bool> {
+        Ok(self.has_moved()? || !self.legacy_sandboxes()?.1.is_empty())
+    }
+
+    /// Whether the repository or the godo directory moved since the project was last recorded
+    /// in the registry.
+    fn has_moved(&self) -> Result<bool> {
+        let projects = ProjectRegistry::new(&self.godo_dir)
+            .projects()
+            .map_err(|e| registry_error(&e))?;
+        if let Some(entry) = projects.get(&self.project_id()?) {
+            let project_dir = self.project_dir()?;
+            return Ok(entry
+                .project_dir
+                .as_ref()
+                .is_some_and(|recorded| *recorded != project_dir));
+        }
+        // An unknown project may be a repository that moved away from its sandboxes.
+        for (old_id, entry) in &projects {
+            if entry.is_stale() && self.is_previous_location(entry, &self.godo_dir.join(old_id))? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Whether `old_dir`, the project directory recorded for the repository in `entry`, holds
+    /// sandboxes of this repository, meaning the repository was moved from there.
+    ///
+    /// A sandbox's `.git` file points at its administrative directory inside the old
+    /// repository. The same directory inside this repository must exist and point back at a
+    /// sandbox directory of that project.
+    fn is_previous_location(&self, entry: &ProjectEntry, old_dir: &Path) -> Result<bool> {
+        let Some(old_id) = old_dir.file_name() else {
+            return Ok(false);
+        };
+        for sandbox in sandbox_dirs(old_dir)? {
+            let Some(gitdir) = git::worktree_gitdir(&old_dir.join(&sandbox)) else {
+                continue;
+            };
+            let Ok(relative) = gitdir.strip_prefix(&entry.repo_dir) else {
+                continue;
+            };
+            let expected = Path::new(old_id).join(&sandbox).join(".git");
+            let linked = fs::read_to_string(self.repo_dir.join(relative).join("gitdir"))
+                .is_ok_and(|target| Path::new(target.trim()).ends_with(&expected));
+            if linked {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Move the sandboxes at `sandboxes`, relative to `from_dir`, into this project's
+    /// directory along with their metadata, returning the names of those moved.
+    ///
+    /// Sandboxes whose name is already taken here are left where they are. Worktree links are
+    /// not touched; callers repair them afterwards.
+    fn adopt_sandboxes(&self, from_dir: &Path, sandboxes: &[PathBuf]) -> Result<Vec<String>> {
+        let project_dir = self.project_dir()?;
+        let from_store = SandboxMetadataStore::new(from_dir);
+        let store = self.metadata_store()?;
+        let metadata_error =
+            |e: anyhow::Error| GodoError::OperationError(format!("Metadata error: {e}"));
+
+        // Both stores stay locked for the whole move, so a concurrent adoption waits and then
+        // finds the sandboxes gone. They are locked in a fixed order to rule out deadlocks.
+        let (lock, from_lock) = if store.base_dir < from_store.base_dir {
+            let lock = store.lock().map_err(metadata_error)?;
+            (lock, from_store.lock().map_err(metadata_error)?)
+        } else {
+            let from_lock = from_store.lock().map_err(metadata_error)?;
+            (store.lock().map_err(metadata_error)?, from_lock)
+        };
+
+        let mut adopted = Vec::new();
+        for relative in sandboxes {
+            let source = from_dir.join(relative);
+            let dest = project_dir.join(relative);
+            if dest.exists() || !source.exists() {
+                continue;
+            }
+            // The metadata is copied before the sandbox moves and only dropped from the old
+            // project afterwards, so a crash never leaves a sandbox without its metadata.
+            let name = path_to_name(relative);
+            let metadata = from_store.read(&name).map_err(metadata_error)?;
+            if let Some(metadata) = &metadata {
+                store
+                    .write_locked(&lock, &name, metadata)
+                    .map_err(metadata_error)?;
+            }
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(&source, &dest)?;
+            if metadata.is_some() {
+                from_store
+                    .remove_locked(&from_lock, &name)
+                    .map_err(metadata_error)?;
+            }
+            if let Some(parent) = source.parent() {
+                naming::prune_empty_dirs(parent, from_dir);
+            }
+            adopted.push(name);
+        }
+        Ok(adopted)
+    }
+
+    /// Move this repository's sandboxes out of the project directory used before projects had
+    /// unique identities.
+    ///
+    /// Older versions kept every repository called `app` in `<godo dir>/app`. Sandboxes whose
+    /// worktrees belong to this repository move to the project directory under the same
+    /// names, with their metadata, and their worktree links are repaired. Sandboxes of other
+    /// repositories are left for those repositories to claim, and the old directory is removed
+    /// once nothing but bookkeeping is left in it. Returns the names of the sandboxes moved.
+    fn migrate_legacy_project(&self) -> Result<Vec<String>> {
+        let (legacy_dir, sandboxes) = self.legacy_sandboxes()?;
+        let moved = self.adopt_sandboxes(&legacy_dir, &sandboxes)?;
+        if moved.is_empty() {
+            return Ok(moved);
+        }
+        let project_dir = self.project_dir()?;
+        let paths: Vec<PathBuf> = moved.iter().map(|name| project_dir.join(name)).collect();
+        git::repair_worktrees(&self.repo_dir, &paths).map_err(|e| git_error(&e))?;
+        self.register_project()?;
+        remove_if_only_bookkeeping(&legacy_dir)?;
+        Ok(moved)
     }
 
-    /// Get the project directory path within the godo directory
-    fn project_dir(&self) -> Result<PathBuf> {
-        let project = project_name(&self.repo_dir)?;
-        Ok(self.godo_dir.join(&project))
+    /// Find this repository's sandboxes in the project directory used before projects had
+    /// unique identities, returning that directory and the sandbox paths relative to it.
+    fn legacy_sandboxes(&self) -> Result<(PathBuf, Vec<PathBuf>)> {
+        let Ok(name) = project_name(&self.repo_dir) else {
+            return Ok((PathBuf::new(), Vec::new()));
+        };
+        let legacy_dir = self.godo_dir.join(name);
+        if !legacy_dir.is_dir() {
+            return Ok((legacy_dir, Vec::new()));
+        }
+
+        let sandboxes = git::list_worktrees(&self.repo_dir)
+            .map_err(|e| git_error(&e))?
+            .into_iter()
+            .filter(|worktree| worktree.path.exists())
+            .filter_map(|worktree| {
+                let relative = worktree.path.strip_prefix(&legacy_dir).ok()?;
+                Some(relative.to_path_buf())
+            })
+            .collect();
+        Ok((legacy_dir, sandboxes))
     }
 
     /// Calculate the path for a sandbox given its name.
@@ -175,36 +589,109 @@ impl Godo {
         Ok(SandboxMetadataStore::new(&self.project_dir()?))
     }
 
+    /// Run `add`, which registers a worktree, while holding the project-wide worktree lock.
+    fn with_worktree_lock<T>(&self, add: impl FnOnce() -> anyhow::Result<T>) -> Result<T> {
+        let _lock = SessionManager::new(&self.project_dir()?).lock_worktrees()?;
+        add().map_err(|e| git_error(&e))
+    }
+
     /// Persist metadata for a newly created sandbox.
-    fn record_metadata(
+    fn record_metadata(&self, sandbox_name: &str, metadata: &SandboxMetadata) -> Result<()> {
+        self.metadata_store()?
+            .write(sandbox_name, metadata)
+            .map_err(|e| GodoError::OperationError(format!("Metadata error: {e}")))?;
+        Ok(())
+    }
+
+    /// Apply `change` to the recorded metadata of a sandbox, if it has any.
+    ///
+    /// The change is made under the metadata lock, so concurrent sessions never lose updates.
+    fn update_metadata(
         &self,
         sandbox_name: &str,
-        base_commit: String,
-        base_ref: Option<String>,
+        change: impl FnOnce(&mut SandboxMetadata),
     ) -> Result<()> {
-        let created_at = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        let metadata = SandboxMetadata {
-            base_commit,
-            base_ref,
-            created_at,
-        };
         self.metadata_store()?
-            .write(sandbox_name, &metadata)
+            .update(sandbox_name, change)
             .map_err(|e| GodoError::OperationError(format!("Metadata error: {e}")))?;
         Ok(())
     }
 
-    /// Remove metadata for a sandbox if present.
+    /// Record that a session is attaching to a sandbox now.
+    fn touch_metadata(&self, sandbox_name: &str) -> Result<()> {
+        self.update_metadata(sandbox_name, |metadata| {
+            metadata.last_used_at = Some(now());
+        })
+    }
+
+    /// Read the recorded metadata of a sandbox: where it started from, how and by whom it was
+    /// created, and when it was last used.
+    ///
+    /// Returns `None` for sandboxes without metadata, such as those whose worktree was removed
+    /// with the branch kept.
+    pub fn metadata(&self, sandbox_name: &str) -> Result<Option<SandboxMetadata>> {
+        validate_sandbox_name(sandbox_name)?;
+        self.read_metadata(sandbox_name)
+    }
+
+    /// Record the exit code of a command that finished running in a sandbox.
+    pub fn record_exit_code(&self, sandbox_name: &str, code: i32) -> Result<()> {
+        self.update_metadata(sandbox_name, |metadata| {
+            metadata.last_exit_code = Some(code);
+        })
+    }
+
+    /// Remove metadata for a sandbox if present, along with its baseline ref.
     fn remove_metadata(&self, sandbox_name: &str) -> Result<()> {
+        git::delete_ref(&self.repo_dir, &self.baseline_ref(sandbox_name))
+            .map_err(|e| git_error(&e))?;
         self.metadata_store()?
             .remove(sandbox_name)
             .map_err(|e| GodoError::OperationError(format!("Metadata error: {e}")))?;
         Ok(())
     }
 
+    /// Read the metadata recorded for a sandbox, if any.
+    fn read_metadata(&self, sandbox_name: &str) -> Result<Option<SandboxMetadata>> {
+        self.metadata_store()?
+            .read(sandbox_name)
+            .map_err(|e| GodoError::OperationError(format!("Metadata error: {e}")))
+    }
+
+    /// Snapshot the sandbox working tree as a baseline commit on top of `base_commit`.
+    ///
+    /// Returns `None` when the working tree does not differ from the base. The commit is kept
+    /// alive by the sandbox's baseline ref, whose creation is journaled in `rollback`.
+    fn record_baseline(
+        &self,
+        sandbox_name: &str,
+        sandbox_path: &Path,
+        base_commit: &str,
+        rollback: &mut CreationRollback,
+    ) -> Result<Option<String>> {
+        let tree = git::snapshot_tree(sandbox_path).map_err(|e| git_error(&e))?;
+        let base_tree = git::tree_id(sandbox_path, base_commit).map_err(|e| git_error(&e))?;
+        if tree == base_tree {
+            return Ok(None);
+        }
+        let commit = git::commit_tree(sandbox_path, &tree, base_commit, "godo baseline")
+            .map_err(|e| git_error(&e))?;
+        self.set_baseline_ref(sandbox_name, &commit, rollback)?;
+        Ok(Some(commit))
+    }
+
+    /// Point the baseline ref of a sandbox at `commit`, journaling it in `rollback`.
+    fn set_baseline_ref(
+        &self,
+        sandbox_name: &str,
+        commit: &str,
+        rollback: &mut CreationRollback,
+    ) -> Result<()> {
+        let name = self.baseline_ref(sandbox_name);
+        rollback.reference(&name);
+        git::update_ref(&self.repo_dir, &name, commit).map_err(|e| git_error(&e))
+    }
+
     /// Get the status for a sandbox or return a not-found error.
     fn require_sandbox_status(&self, name: &str) -> Result<SandboxStatus> {
         match self.get_sandbox(name)? {
@@ -228,31 +715,106 @@ impl Godo {
         self.sandbox_path(name)
     }
 
+    /// Get the sandbox worktree path when it can be committed to, refusing detached sandboxes.
+    fn require_committable_path(&self, name: &str) -> Result<PathBuf> {
+        let status = self.require_sandbox_status(name)?;
+        if status.detached {
+            return Err(GodoError::SandboxError {
+                name: name.to_string(),
+                message: "is detached and has no branch to commit to".to_string(),
+            });
+        }
+        self.require_worktree_path(name)
+    }
+
     /// Check whether the source repository has uncommitted changes.
     pub fn repo_has_uncommitted_changes(&self) -> Result<bool> {
         git::has_uncommitted_changes(&self.repo_dir).map_err(|e| git_error(&e))
     }
 
+    /// Check whether files can be cloned copy-on-write from the repository into the godo
+    /// directory. Creates the project directory if needed.
+    ///
+    /// Returns `None` when the repository has no readable file to probe with.
+    pub fn supports_cow(&self) -> Result<Option<bool>> {
+        let project_dir = self.project_dir()?;
+        fs::create_dir_all(&project_dir)?;
+        clone::reflink_supported(&self.repo_dir, &project_dir)
+    }
+
     /// Create or reuse a sandbox and acquire a session lease for it.
     pub fn prepare_sandbox(
         &self,
         sandbox_name: &str,
         options: PrepareSandboxOptions,
     ) -> Result<PrepareSandboxPlan> {
-        validate_sandbox_name(sandbox_name)?;
+        self.prepare_sandbox_with_events(sandbox_name, options, &mut |_| {})
+    }
 
-        let sandbox_path = self.sandbox_path(sandbox_name)?;
-        let project_dir = self.project_dir()?;
-        let session_manager = SessionManager::new(&project_dir);
+    /// Create or reuse a sandbox, reporting progress through `on_event`.
+    ///
+    /// Creation is transactional: if any step fails, including setup hooks and acquiring the
+    /// session lease, everything created so far is rolled back before the error is returned.
+    pub fn prepare_sandbox_with_events(
+        &self,
+        sandbox_name: &str,
+        options: PrepareSandboxOptions,
+        on_event: &mut dyn FnMut(PrepareEvent),
+    ) -> Result<PrepareSandboxPlan> {
+        validate_sandbox_name(sandbox_name)?;
 
         // Acquire lock to ensure exclusive access during creation/verification
-        let locked_session = session_manager.lock(sandbox_name)?;
+        let locked_session = SessionManager::new(&self.project_dir()?).lock(sandbox_name)?;
+        self.prepare_locked(sandbox_name, locked_session, options, on_event)
+    }
 
-        let existing_sandbox = self.get_sandbox(sandbox_name)?;
-        let mut created = false;
-        let mut cleaned = false;
+    /// Reserve a fresh, generated sandbox name such as `brisk-otter`.
+    ///
+    /// Candidates are tried until one has no branch, worktree or directory. Each check is made
+    /// while holding the candidate's session lock, and the lock is kept in the returned
+    /// reservation, so concurrent callers never receive the same name. Candidates whose lock
+    /// is held elsewhere are in use and skipped without waiting.
+    pub fn reserve_sandbox_name(&self) -> Result<SandboxReservation> {
+        let session_manager = SessionManager::new(&self.project_dir()?);
+        let mut attempt = 0;
+        loop {
+            let name = naming::generate_sandbox_name(attempt);
+            attempt += 1;
+            let Some(lock) = session_manager.try_lock(&name)? else {
+                continue;
+            };
+            if self.get_sandbox(&name)?.is_none() {
+                return Ok(SandboxReservation { name, lock });
+            }
+        }
+    }
+
+    /// Create a sandbox under a name reserved with [`Self::reserve_sandbox_name`], reporting
+    /// progress through `on_event`.
+    ///
+    /// Behaves like [`Self::prepare_sandbox_with_events`], without releasing the reservation's
+    /// lock in between.
+    pub fn prepare_reserved_sandbox(
+        &self,
+        reservation: SandboxReservation,
+        options: PrepareSandboxOptions,
+        on_event: &mut dyn FnMut(PrepareEvent),
+    ) -> Result<PrepareSandboxPlan> {
+        let SandboxReservation { name, lock } = reservation;
+        self.prepare_locked(&name, lock, options, on_event)
+    }
 
-        if let Some(sandbox) = existing_sandbox {
+    /// Create or reuse a sandbox while holding its session lock, then acquire a lease for it.
+    fn prepare_locked(
+        &self,
+        sandbox_name: &str,
+        locked_session: LockedSandbox,
+        options: PrepareSandboxOptions,
+        on_event: &mut dyn FnMut(PrepareEvent),
+    ) -> Result<PrepareSandboxPlan> {
+        let sandbox_path = self.sandbox_path(sandbox_name)?;
+        let existing_sandbox = self.get_sandbox(sandbox_name)?;
+        let (created, rollback) = if let Some(sandbox) = existing_sandbox {
             if !sandbox.is_live() {
                 let status = sandbox.component_status();
                 return Err(GodoError::SandboxError {
@@ -260,140 +822,607 @@ impl Godo {
                     message: format!("exists but is not live - remove it first ({status})"),
                 });
             }
+            (None, None)
         } else {
-            let PrepareSandboxOptions {
-                uncommitted_policy,
-                excludes,
-            } = options;
-            let has_uncommitted = self.repo_has_uncommitted_changes()?;
-            let use_clean_branch = matches!(uncommitted_policy, UncommittedPolicy::Clean);
-
-            if has_uncommitted && matches!(uncommitted_policy, UncommittedPolicy::Abort) {
-                return Err(GodoError::UncommittedChanges {
-                    repo_dir: self.repo_dir.clone(),
-                });
-            }
-
-            // Ensure project directory exists
-            fs::create_dir_all(&project_dir)?;
-
-            let base_commit = git::rev_parse(&self.repo_dir, "HEAD").map_err(|e| git_error(&e))?;
-            let base_ref = git::head_ref(&self.repo_dir).map_err(|e| git_error(&e))?;
-
-            let branch = branch_name(sandbox_name);
-            git::create_worktree(&self.repo_dir, &sandbox_path, &branch)
-                .map_err(|e| git_error(&e))?;
-
-            // Clone each top-level entry from repo to sandbox, skipping .git.
-            // We do this entry-by-entry because clone_tree requires the destination
-            // not to exist, but the worktree already created the sandbox with .git.
-            for entry in fs::read_dir(&self.repo_dir)? {
-                let entry = entry?;
-                let name = entry.file_name();
-                if name == ".git" {
-                    continue;
-                }
-
-                // Check user excludes
-                let name_str = name.to_string_lossy();
-                if excludes.iter().any(|ex| name_str == *ex) {
-                    continue;
-                }
-
-                let src = entry.path();
-                let dest = sandbox_path.join(&name);
-
-                // Remove existing entry in sandbox (from worktree checkout)
-                if dest.exists() || dest.is_symlink() {
-                    if dest.is_dir() && !dest.is_symlink() {
-                        fs::remove_dir_all(&dest)?;
-                    } else {
-                        fs::remove_file(&dest)?;
-                    }
-                }
-
-                if src.is_dir() && !src.is_symlink() {
-                    clone_tree(&src, &dest, &Options::new()).map_err(|e| {
-                        GodoError::OperationError(format!(
-                            "Failed to clone {:?} to sandbox: {e}",
-                            name
-                        ))
-                    })?;
-                } else if src.is_symlink() {
-                    let target = fs::read_link(&src)?;
-                    #[cfg(unix)]
-                    symlink(&target, &dest)?;
-                    #[cfg(windows)]
-                    {
-                        if target.is_dir() {
-                            symlink_dir(&target, &dest)?;
-                        } else {
-                            symlink_file(&target, &dest)?;
-                        }
-                    }
-                } else {
-                    reflink_copy::reflink_or_copy(&src, &dest).map_err(|e| {
-                        GodoError::OperationError(format!(
-                            "Failed to copy {:?} to sandbox: {e}",
-                            name
-                        ))
-                    })?;
+            self.check_nesting(sandbox_name)?;
+            self.register_project()?;
+            let mut rollback =
+                CreationRollback::new(&self.repo_dir, &self.project_dir()?, self.metadata_store()?);
+            match self.create_sandbox(
+                sandbox_name,
+                &sandbox_path,
+                options,
+                &mut rollback,
+                on_event,
+            ) {
+                Ok(created) => (Some(created), Some(rollback)),
+                Err(err) => {
+                    rollback.roll_back();
+                    return Err(err);
                 }
             }
-
-            if has_uncommitted && use_clean_branch {
-                git::reset_hard(&sandbox_path)
-                    .map_err(|e| GodoError::GitError(format!("Failed to reset sandbox: {e}")))?;
-                git::clean(&sandbox_path)
-                    .map_err(|e| GodoError::GitError(format!("Failed to clean sandbox: {e}")))?;
-                cleaned = true;
-            }
-
-            self.record_metadata(sandbox_name, base_commit, base_ref)?;
-            created = true;
-        }
+        };
 
         // Acquire session lease to track concurrent connections.
-        let lease = locked_session.acquire_lease()?;
+        let lease = match locked_session.acquire_lease().and_then(|lease| {
+            self.touch_metadata(sandbox_name)?;
+            Ok(lease)
+        }) {
+            Ok(lease) => lease,
+            Err(err) => {
+                if let Some(rollback) = rollback {
+                    rollback.roll_back();
+                }
+                return Err(err);
+            }
+        };
         let session = SandboxSession {
             name: sandbox_name.to_string(),
             path: sandbox_path,
             lease,
         };
 
+        let is_created = created.is_some();
+        let CreatedSandbox {
+            cleaned,
+            hooks_run,
+            clone_stats,
+        } = created.unwrap_or_default();
         Ok(PrepareSandboxPlan {
             session,
-            created,
+            created: is_created,
             cleaned,
+            hooks_run,
+            clone_stats,
         })
     }
 
-    /// Plan a diff for a sandbox against its recorded base commit.
-    pub fn diff_plan(&self, sandbox_name: &str, base_override: Option<&str>) -> Result<DiffPlan> {
+    /// Create sandbox `sandbox_name` as a fork of the existing sandbox `source_name`.
+    ///
+    /// The new branch starts at the source sandbox's `HEAD`, and the source's working tree,
+    /// including uncommitted changes and ignored build artifacts, is cloned into the new
+    /// worktree. The fork inherits the source's recorded base, so diffs cover both, along with
+    /// its command and clone settings. A session lease is held on the source while its tree is
+    /// copied, so a session ending there cannot clean it up mid-copy. As with
+    /// [`Self::prepare_sandbox`], a failed fork is rolled back.
+    pub fn fork_sandbox(
+        &self,
+        source_name: &str,
+        sandbox_name: &str,
+        on_event: &mut dyn FnMut(PrepareEvent),
+    ) -> Result<CloneStats> {
+        validate_sandbox_name(source_name)?;
         validate_sandbox_name(sandbox_name)?;
+        let source_path = self.require_worktree_path(source_name)?;
 
-        let sandbox = match self.get_sandbox(sandbox_name)? {
-            Some(status) => status,
-            None => {
-                return Err(GodoError::SandboxError {
-                    name: sandbox_name.to_string(),
-                    message: "does not exist".to_string(),
-                });
-            }
-        };
-
-        if !sandbox.is_live() {
-            let status = sandbox.component_status();
+        let project_dir = self.project_dir()?;
+        let session_manager = SessionManager::new(&project_dir);
+        // The source lease is taken before the fork's lock, so two forks in opposite directions
+        // cannot wait on each other.
+        let _source_lease = session_manager.lock(source_name)?.acquire_lease()?;
+        let _locked_session = session_manager.lock(sandbox_name)?;
+        if self.get_sandbox(sandbox_name)?.is_some() {
             return Err(GodoError::SandboxError {
                 name: sandbox_name.to_string(),
-                message: format!("exists but is not live - remove it first ({status})"),
+                message: "already exists".to_string(),
             });
         }
+        self.check_nesting(sandbox_name)?;
+        self.register_project()?;
+
+        let mut rollback =
+            CreationRollback::new(&self.repo_dir, &self.project_dir()?, self.metadata_store()?);
+        let result = self.create_fork(
+            &source_path,
+            source_name,
+            sandbox_name,
+            &mut rollback,
+            on_event,
+        );
+        if result.is_err() {
+            rollback.roll_back();
+        }
+        result
+    }
+
+    /// Create a fork of the sandbox at `source_path`, journaling each step in `rollback`.
+    fn create_fork(
+        &self,
+        source_path: &Path,
+        source_name: &str,
+        sandbox_name: &str,
+        rollback: &mut CreationRollback,
+        on_event: &mut dyn FnMut(PrepareEvent),
+    ) -> Result<CloneStats> {
+        let start_commit = git::rev_parse(source_path, "HEAD").map_err(|e| git_error(&e))?;
+        let source = self
+            .read_metadata(source_name)?
+            .unwrap_or_else(|| SandboxMetadata {
+                base_commit: start_commit.clone(),
+                ..SandboxMetadata::default()
+            });
+
+        let project_dir = self.project_dir()?;
+        let reflink_supported = clone::reflink_supported(source_path, &project_dir)? == Some(true);
+        // A fork of a sparse sandbox covers the same cone.
+        let sparse_paths: Vec<String> = git::sparse_checkout_paths(source_path)
+            .map_err(|e| git_error(&e))?
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        let excludes = ExcludeSet::new(source_path, &[])?.with_sparse_paths(&sparse_paths)?;
+        let sandbox_path = self.sandbox_path(sandbox_name)?;
+        let branch = self.branch_name(sandbox_name);
+
+        rollback.worktree(&sandbox_path, &branch);
+        self.with_worktree_lock(|| {
+            git::create_worktree_at(
+                &self.repo_dir,
+                &sandbox_path,
+                &branch,
+                Some(&start_commit),
+                false,
+            )
+        })?;
+        let stats = self.populate_from_working_tree(
+            source_path,
+            &sandbox_path,
+            &excludes,
+            reflink_supported,
+            on_event,
+        )?;
+
+        // The fork keeps measuring changes from the source's baseline.
+        if let Some(baseline) = &source.baseline_commit {
+            self.set_baseline_ref(sandbox_name, baseline, rollback)?;
+        }
+        rollback.metadata(sandbox_name);
+        self.record_metadata(
+            sandbox_name,
+            &SandboxMetadata {
+                created_at: now(),
+                detached: false,
+                user: current_user(),
+                host: System::host_name(),
+                last_used_at: None,
+                last_exit_code: None,
+                ..source
+            },
+        )?;
+        Ok(stats)
+    }
+
+    /// Create a new sandbox, journaling each step in `rollback` before it is taken.
+    fn create_sandbox(
+        &self,
+        sandbox_name: &str,
+        sandbox_path: &Path,
+        options: PrepareSandboxOptions,
+        rollback: &mut CreationRollback,
+        on_event: &mut dyn FnMut(PrepareEvent),
+    ) -> Result<CreatedSandbox> {
+        let PrepareSandboxOptions {
+            uncommitted_policy,
+            excludes: exclude_patterns,
+            base,
+            setup_hooks,
+            require_cow,
+            sparse_paths,
+            detached,
+            command,
+        } = options;
+        let excludes =
+            ExcludeSet::new(&self.repo_dir, &exclude_patterns)?.with_sparse_paths(&sparse_paths)?;
+        let branch = self.branch_name(sandbox_name);
+        let mut cleaned = false;
+
+        let from_rev = base.is_some();
+        let (base_commit, base_ref) = if let Some(rev) = base {
+            let base_commit =
+                git::rev_parse(&self.repo_dir, &rev).map_err(|e| GodoError::BaseError {
+                    name: sandbox_name.to_string(),
+                    message: format!("revision '{rev}' could not be resolved: {e}"),
+                })?;
+            (base_commit, Some(rev))
+        } else {
+            if !git::has_head_commit(&self.repo_dir) {
+                return Err(GodoError::BaseError {
+                    name: sandbox_name.to_string(),
+                    message: "the repository has no commits yet; commit something first"
+                        .to_string(),
+                });
+            }
+            let has_uncommitted = self.repo_has_uncommitted_changes()?;
+            if has_uncommitted && matches!(uncommitted_policy, UncommittedPolicy::Abort) {
+                return Err(GodoError::UncommittedChanges {
+                    repo_dir: self.repo_dir.clone(),
+                });
+            }
+            cleaned = has_uncommitted && matches!(uncommitted_policy, UncommittedPolicy::Clean);
+
+            let base_commit = git::rev_parse(&self.repo_dir, "HEAD").map_err(|e| git_error(&e))?;
+            let base_ref = git::head_ref(&self.repo_dir).map_err(|e| git_error(&e))?;
+            (base_commit, base_ref)
+        };
+        // The source working tree only matches the checkout when starting from HEAD with
+        // its changes included.
+        let from_working_tree = !from_rev && !cleaned;
+
+        // Ensure project directory exists
+        let project_dir = self.project_dir()?;
+        fs::create_dir_all(&project_dir)?;
+        // Support that cannot be confirmed does not satisfy `require_cow`.
+        let reflink_supported =
+            clone::reflink_supported(&self.repo_dir, &project_dir)? == Some(true);
+        if require_cow && !reflink_supported {
+            return Err(GodoError::CopyOnWriteUnavailable {
+                repo_dir: self.repo_dir.clone(),
+                godo_dir: project_dir,
+            });
+        }
+
+        let mut add_worktree = |checkout: bool| {
+            if detached {
+                rollback.detached_worktree(sandbox_path);
+                self.with_worktree_lock(|| {
+                    git::create_detached_worktree(
+                        &self.repo_dir,
+                        sandbox_path,
+                        &base_commit,
+                        checkout,
+                    )
+                })
+            } else {
+                rollback.worktree(sandbox_path, &branch);
+                self.with_worktree_lock(|| {
+                    git::create_worktree_at(
+                        &self.repo_dir,
+                        sandbox_path,
+                        &branch,
+                        Some(&base_commit),
+                        checkout,
+                    )
+                })
+            }
+        };
+
+        let mut baseline_commit = None;
+        let clone_stats = if from_working_tree {
+            // Skip the checkout: every file is written exactly once, by the clone.
+            add_worktree(false)?;
+            let source_dir = self.repo_dir.clone();
+            let stats = self.populate_from_working_tree(
+                &source_dir,
+                sandbox_path,
+                &excludes,
+                reflink_supported,
+                on_event,
+            )?;
+            // Remember the included changes so they can be told apart from the command's.
+            baseline_commit =
+                self.record_baseline(sandbox_name, sandbox_path, &base_commit, rollback)?;
+            stats
+        } else {
+            // Tracked files come from a checkout of the base; only carry over ignored files such
+            // as build caches from the source working tree.
+            let sparse = excludes.sparse_paths();
+            add_worktree(sparse.is_empty())?;
+            if !sparse.is_empty() {
+                git::sparse_checkout_set(sandbox_path, sparse).map_err(|e| git_error(&e))?;
+                git::checkout_head(sandbox_path).map_err(|e| git_error(&e))?;
+            }
+            let source_dir = self.repo_dir.clone();
+            self.populate_from_checkout(
+                &source_dir,
+                sandbox_path,
+                &excludes,
+                reflink_supported,
+                on_event,
+            )?
+        };
+
+        let mut hooks_run = Vec::new();
+        for command in setup_hooks {
+            on_event(PrepareEvent::HookStarted {
+                command: command.clone(),
+            });
+            hooks::run_setup_hook(sandbox_path, &command, &mut |line| {
+                on_event(PrepareEvent::HookOutput {
+                    command: command.clone(),
+                    line: line.to_string(),
+                });
+            })?;
+            hooks_run.push(command);
+        }
+
+        rollback.metadata(sandbox_name);
+        self.record_metadata(
+            sandbox_name,
+            &SandboxMetadata {
+                base_commit,
+                base_ref,
+                created_at: now(),
+                baseline_commit,
+                detached,
+                command,
+                uncommitted_policy: (!from_rev).then_some(uncommitted_policy),
+                excludes: exclude_patterns,
+                user: current_user(),
+                host: System::host_name(),
+                last_used_at: None,
+                last_exit_code: None,
+            },
+        )?;
+
+        Ok(CreatedSandbox {
+            cleaned,
+            hooks_run,
+            clone_stats,
+        })
+    }
+
+    /// Populate an unchecked-out worktree from the working tree at `source_dir`.
+    ///
+    /// The index is filled from the source's index and the working tree is cloned once, so the
+    /// sandbox reports the same status as the source. Tracked files that are excluded from the
+    /// clone are restored from the index so they do not show up as deletions, unless they are
+    /// deleted in the source too. Submodules are skipped by the clone and attached as worktrees
+    /// of their own.
+    fn populate_from_working_tree(
+        &self,
+        source_dir: &Path,
+        sandbox_path: &Path,
+        excludes: &ExcludeSet,
+        reflink_supported: bool,
+        on_event: &mut dyn FnMut(PrepareEvent),
+    ) -> Result<CloneStats> {
+        // Stage what the source has staged, so the sandbox splits its changes the same way. A
+        // conflicted index cannot be carried over; the sandbox then starts from `HEAD`.
+        let index = git::index_tree(source_dir).map_err(|e| git_error(&e))?;
+        git::read_tree(sandbox_path, index.as_deref().unwrap_or("HEAD"))
+            .map_err(|e| git_error(&e))?;
+        let submodules = git::submodules(source_dir).map_err(|e| git_error(&e))?;
+        let submodule_patterns: Vec<String> = submodules
+            .iter()
+            .map(|submodule| format!("/{}/", submodule.path.display()))
+            .collect();
+        let clone_excludes = excludes.with_patterns(source_dir, &submodule_patterns)?;
+        let mut stats = self.clone_working_tree(
+            source_dir,
+            sandbox_path,
+            &clone_excludes,
+            reflink_supported,
+            on_event,
+        )?;
+
+        let sparse = excludes.sparse_paths();
+        if !sparse.is_empty() {
+            git::sparse_checkout_set(sandbox_path, sparse).map_err(|e| git_error(&e))?;
+        }
+
+        let excluded_tracked: Vec<PathBuf> = git::tracked_files(sandbox_path)
+            .map_err(|e| git_error(&e))?
+            .into_iter()
+            .filter(|path| excludes.excludes_path(path, false))
+            .filter(|path| !excludes.outside_cone(path, false))
+            .filter(|path| !submodules.iter().any(|submodule| &submodule.path == path))
+            // Files deleted in the source stay deleted, as they would have been cloned.
+            .filter(|path| fs::symlink_metadata(source_dir.join(path)).is_ok())
+            .collect();
+        git::checkout_index(sandbox_path, &excluded_tracked).map_err(|e| git_error(&e))?;
+
+        stats += self.attach_submodules(
+            source_dir,
+            sandbox_path,
+            excludes,
+            reflink_supported,
+            true,
+            on_event,
+        )?;
+        git::refresh_index(sandbox_path).map_err(|e| git_error(&e))?;
+        Ok(stats)
+    }
+
+    /// Carry ignored files and submodules over from `source_dir` into a checked-out worktree.
+    fn populate_from_checkout(
+        &self,
+        source_dir: &Path,
+        sandbox_path: &Path,
+        excludes: &ExcludeSet,
+        reflink_supported: bool,
+        on_event: &mut dyn FnMut(PrepareEvent),
+    ) -> Result<CloneStats> {
+        let mut stats = self.clone_ignored_files(
+            source_dir,
+            sandbox_path,
+            excludes,
+            reflink_supported,
+            on_event,
+        )?;
+        stats += self.attach_submodules(
+            source_dir,
+            sandbox_path,
+            excludes,
+            reflink_supported,
+            false,
+            on_event,
+        )?;
+        Ok(stats)
+    }
+
+    /// Give each submodule of the sandbox a worktree of the matching source submodule.
+    ///
+    /// When `from_working_tree` is set, each submodule starts at the source submodule's `HEAD`
+    /// and its working tree is cloned; otherwise it is checked out at the commit the sandbox
+    /// records and only ignored files are cloned. Excluded submodules are skipped, and those not
+    /// populated in the source are left as empty directories, as Git does.
+    fn attach_submodules(
+        &self,
+        source_dir: &Path,
+        sandbox_path: &Path,
+        excludes: &ExcludeSet,
+        reflink_supported: bool,
+        from_working_tree: bool,
+        on_event: &mut dyn FnMut(PrepareEvent),
+    ) -> Result<CloneStats> {
+        // An unchecked-out sandbox starts at the source's HEAD, so both record the same
+        // submodules; a checked-out one records its own.
+        let recorded_in = if from_working_tree {
+            source_dir
+        } else {
+            sandbox_path
+        };
+        let submodules = git::submodules(recorded_in).map_err(|e| git_error(&e))?;
+        let mut stats = CloneStats {
+            copy_on_write: reflink_supported,
+            ..CloneStats::default()
+        };
+        for submodule in submodules {
+            let source = source_dir.join(&submodule.path);
+            let dest = sandbox_path.join(&submodule.path);
+            if excludes.excludes_path(&submodule.path, true) {
+                continue;
+            }
+            if !source.join(".git").exists() {
+                fs::create_dir_all(&dest)?;
+                continue;
+            }
+
+            let entry = submodule.path.to_string_lossy();
+            let submodule_excludes = excludes.within(&source, &entry)?;
+            if from_working_tree {
+                if let Some(parent) = dest.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let commit = git::rev_parse(&source, "HEAD").map_err(|e| git_error(&e))?;
+                self.with_worktree_lock(|| {
+                    git::create_detached_worktree(&source, &dest, &commit, false)
+                })?;
+                stats += self.populate_from_working_tree(
+                    &source,
+                    &dest,
+                    &submodule_excludes,
+                    reflink_supported,
+                    on_event,
+                )?;
+            } else {
+                let sparse = submodule_excludes.sparse_paths();
+                self.with_worktree_lock(|| {
+                    git::create_detached_worktree(
+                        &source,
+                        &dest,
+                        &submodule.commit,
+                        sparse.is_empty(),
+                    )
+                })?;
+                if !sparse.is_empty() {
+                    git::sparse_checkout_set(&dest, sparse).map_err(|e| git_error(&e))?;
+                    git::checkout_head(&dest).map_err(|e| git_error(&e))?;
+                }
+                stats += self.populate_from_checkout(
+                    &source,
+                    &dest,
+                    &submodule_excludes,
+                    reflink_supported,
+                    on_event,
+                )?;
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Clone the working tree at `source_dir` into a freshly created worktree, skipping `.git`.
+    fn clone_working_tree(
+        &self,
+        source_dir: &Path,
+        sandbox_path: &Path,
+        excludes: &ExcludeSet,
+        reflink_supported: bool,
+        on_event: &mut dyn FnMut(PrepareEvent),
+    ) -> Result<CloneStats> {
+        // Clone each top-level entry from repo to sandbox, skipping .git, which the worktree
+        // already created. Directories leading to a sparse path are walked rather than cloned
+        // whole.
+        let mut jobs = Vec::new();
+        let mut pending = vec![PathBuf::new()];
+        while let Some(relative) = pending.pop() {
+            for entry in fs::read_dir(source_dir.join(&relative))? {
+                let entry = entry?;
+                let name = entry.file_name();
+                if relative.as_os_str().is_empty() && name == ".git" {
+                    continue;
+                }
+
+                let src = entry.path();
+                let src_is_dir = src.is_dir() && !src.is_symlink();
+                let path = relative.join(&name);
+                if excludes.excludes_path(&path, src_is_dir) {
+                    continue;
+                }
+
+                let dest = sandbox_path.join(&path);
+                if src_is_dir && excludes.partially_included(&path) {
+                    fs::create_dir_all(&dest)?;
+                    pending.push(path);
+                    continue;
+                }
+                jobs.push(CloneJob {
+                    src,
+                    dest,
+                    entry: path.to_string_lossy().into_owned(),
+                });
+            }
+        }
+
+        clone::clone_all(&jobs, excludes, reflink_supported, &mut |progress| {
+            on_event(PrepareEvent::Clone(progress));
+        })
+    }
+
+    /// Clone the ignored files of the working tree at `source_dir` into a checked-out worktree.
+    fn clone_ignored_files(
+        &self,
+        source_dir: &Path,
+        sandbox_path: &Path,
+        excludes: &ExcludeSet,
+        reflink_supported: bool,
+        on_event: &mut dyn FnMut(PrepareEvent),
+    ) -> Result<CloneStats> {
+        let ignored = git::ignored_paths(source_dir).map_err(|e| git_error(&e))?;
+        let mut jobs = Vec::new();
+        for relative in ignored {
+            let src = source_dir.join(&relative);
+            let src_is_dir = src.is_dir() && !src.is_symlink();
+            if excludes.excludes_path(&relative, src_is_dir) {
+                continue;
+            }
+
+            // Paths tracked at the checked-out revision win over ignored files in the source.
+            let dest = sandbox_path.join(&relative);
+            if dest.exists() || dest.is_symlink() {
+                continue;
+            }
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            jobs.push(CloneJob {
+                src,
+                dest,
+                entry: relative.to_string_lossy().trim_end_matches('/').to_string(),
+            });
+        }
+
+        clone::clone_all(&jobs, excludes, reflink_supported, &mut |progress| {
+            on_event(PrepareEvent::Clone(progress));
+        })
+    }
+
+    /// Plan a diff for a sandbox against its recorded base commit.
+    pub fn diff_plan(&self, sandbox_name: &str, base_override: Option<&str>) -> Result<DiffPlan> {
+        let sandbox_path = self.live_sandbox_path(sandbox_name)?;
+        let base = self.resolve_base_commit(sandbox_name, base_override)?;
+        let untracked_files = git::untracked_files(&sandbox_path).map_err(|e| git_error(&e))?;
 
-        let sandbox_path = self.sandbox_path(sandbox_name)?;
-        let base = self.resolve_base_commit(sandbox_name, base_override)?;
-        let untracked_files = git::untracked_files(&sandbox_path).map_err(|e| git_error(&e))?;
-
         Ok(DiffPlan {
             sandbox_name: sandbox_name.to_string(),
             sandbox_path,
@@ -401,9 +1430,60 @@ impl Godo {
             used_fallback: base.used_fallback,
             fallback_target: base.fallback_target,
             untracked_files,
+            target_tree: None,
+        })
+    }
+
+    /// Plan a diff of only the changes made inside a sandbox.
+    ///
+    /// The sandbox is compared with its baseline, so uncommitted changes included when it was
+    /// created are left out. Sandboxes without a baseline are diffed against their base commit.
+    pub fn delta_diff_plan(&self, sandbox_name: &str) -> Result<DiffPlan> {
+        let sandbox_path = self.live_sandbox_path(sandbox_name)?;
+        let Some(baseline) = self
+            .read_metadata(sandbox_name)?
+            .and_then(|metadata| metadata.baseline_commit)
+        else {
+            return self.diff_plan(sandbox_name, None);
+        };
+        let snapshot = git::snapshot_tree(&sandbox_path).map_err(|e| git_error(&e))?;
+
+        Ok(DiffPlan {
+            sandbox_name: sandbox_name.to_string(),
+            sandbox_path,
+            base_commit: baseline,
+            used_fallback: false,
+            fallback_target: None,
+            untracked_files: Vec::new(),
+            target_tree: Some(snapshot),
         })
     }
 
+    /// Resolve the worktree path of a sandbox, failing unless the sandbox is live.
+    fn live_sandbox_path(&self, sandbox_name: &str) -> Result<PathBuf> {
+        validate_sandbox_name(sandbox_name)?;
+
+        let sandbox = match self.get_sandbox(sandbox_name)? {
+            Some(status) => status,
+            None => {
+                return Err(GodoError::SandboxError {
+                    name: sandbox_name.to_string(),
+                    message: "does not exist".to_string(),
+                });
+            }
+        };
+
+        if !sandbox.is_live() {
+            let status = sandbox.component_status();
+            return Err(GodoError::SandboxError {
+                name: sandbox_name.to_string(),
+                message: format!("exists but is not live - remove it first ({status})"),
+            });
+        }
+
+        self.sandbox_path(sandbox_name)
+    }
+
     /// Resolve the base commit for a sandbox diff.
     fn resolve_base_commit(
         &self,
@@ -443,7 +1523,7 @@ impl Godo {
                 fallback_target: None,
             }),
             Err(_) => {
-                let branch = branch_name(sandbox_name);
+                let branch = self.branch_name(sandbox_name);
                 let mut candidates = Vec::new();
 
                 // First priority: the recorded base_ref from metadata
@@ -497,7 +1577,7 @@ impl Godo {
     /// Get the status of a sandbox by name.
     fn get_sandbox(&self, name: &str) -> Result<Option<SandboxStatus>> {
         let sandbox_path = self.sandbox_path(name)?;
-        let branch_name = branch_name(name);
+        let branch_name = self.branch_name(name);
 
         // Check if branch exists
         let has_branch =
@@ -527,14 +1607,23 @@ impl Godo {
             }
         }
 
-        // Check if the worktree directory exists
-        let has_worktree_dir = sandbox_path.exists();
+        // Check if the worktree directory exists; a namespace directory such as `bug/` holding
+        // other sandboxes does not count.
+        let has_worktree_dir = sandbox_path.exists()
+            && (has_worktree || !self.is_namespace_dir(name, &self.namespace_hints()?)?);
 
         // If neither branch, worktree, nor directory exists, the sandbox doesn't exist
         if !has_branch && !has_worktree && !has_worktree_dir {
             return Ok(None);
         }
 
+        // Unreadable metadata is reported elsewhere; treat the sandbox as branch-backed here.
+        let detached = self
+            .read_metadata(name)
+            .ok()
+            .flatten()
+            .is_some_and(|metadata| metadata.detached);
+
         // Determine merge status relative to integration target (only if branch exists)
         let (merge_status, unmerged_commits) = if has_branch {
             let status = git::branch_merge_status(&self.repo_dir, &branch_name)
@@ -551,8 +1640,9 @@ impl Godo {
 
         // Check if dangling:
         //  - Git records a worktree but the directory is gone, or
-        //  - A directory exists but no branch backs it.
-        let is_dangling = (has_worktree && !has_worktree_dir) || (has_worktree_dir && !has_branch);
+        //  - A directory exists but no branch backs it, and none is expected to.
+        let is_dangling =
+            (has_worktree && !has_worktree_dir) || (has_worktree_dir && !has_branch && !detached);
 
         // Check for uncommitted changes (only if worktree exists)
         let (has_uncommitted_changes, diff_stats) = if has_worktree && has_worktree_dir {
@@ -569,6 +1659,7 @@ impl Godo {
 
         Ok(Some(SandboxStatus {
             name: name.to_string(),
+            branch: branch_name,
             has_branch,
             has_worktree,
             has_worktree_dir,
@@ -580,40 +1671,92 @@ impl Godo {
             merge_status,
             unmerged_commits,
             is_dangling,
+            detached,
         }))
     }
 
-    /// Gather every sandbox name present in branches, worktrees, or on disk.
-    fn all_sandbox_names(&self) -> Result<Vec<String>> {
+    /// Gather the sandbox names recorded by branches in the namespace and by worktrees inside
+    /// the project directory.
+    fn recorded_sandbox_names(&self) -> Result<HashSet<String>> {
         let project_dir = self.project_dir()?;
-
-        let mut all_names = HashSet::new();
+        let mut names = HashSet::new();
 
         let all_branches = git::list_branches(&self.repo_dir).map_err(|e| git_error(&e))?;
         for branch in &all_branches {
-            if let Some(name) = branch.strip_prefix("godo/") {
-                all_names.insert(name.to_string());
+            if let Some(name) = self.branches.sandbox_name(branch) {
+                names.insert(name.to_string());
             }
         }
 
         for worktree in git::list_worktrees(&self.repo_dir).map_err(|e| git_error(&e))? {
             if let Some(branch) = &worktree.branch {
-                let branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
-                if let Some(name) = branch.strip_prefix("godo/") {
-                    all_names.insert(name.to_string());
+                if let Some(name) = self.branches.sandbox_name(branch) {
+                    names.insert(name.to_string());
                 }
+            } else if let Ok(relative) = worktree.path.strip_prefix(&project_dir) {
+                // Detached sandboxes have no branch; their path is their name.
+                names.insert(path_to_name(relative));
             }
         }
 
-        if project_dir.exists() {
-            for entry in fs::read_dir(&project_dir)? {
+        Ok(names)
+    }
+
+    /// Gather the names of every sandbox that can hold a namespace directory: recorded names
+    /// and names with metadata.
+    fn namespace_hints(&self) -> Result<HashSet<String>> {
+        let mut hints = self.recorded_sandbox_names()?;
+        hints.extend(
+            self.metadata_store()?
+                .names()
+                .map_err(|e| GodoError::OperationError(format!("Metadata error: {e}")))?,
+        );
+        Ok(hints)
+    }
+
+    /// Whether the directory of `name` is only a namespace for other sandboxes, as `bug/` is
+    /// for `bug/1234`, rather than a sandbox of its own.
+    fn is_namespace_dir(&self, name: &str, hints: &HashSet<String>) -> Result<bool> {
+        Ok(!self.sandbox_path(name)?.join(".git").exists()
+            && hints.iter().any(|hint| naming::is_nested(hint, name)))
+    }
+
+    /// Gather every sandbox name present in branches, worktrees, or on disk.
+    ///
+    /// Directories are walked into namespaces such as `bug/`; any other directory counts as a
+    /// sandbox, even without a worktree.
+    fn all_sandbox_names(&self) -> Result<Vec<String>> {
+        let project_dir = self.project_dir()?;
+
+        let mut all_names = self.recorded_sandbox_names()?;
+        let hints = self.namespace_hints()?;
+
+        let mut pending = vec![String::new()];
+        while let Some(namespace) = pending.pop() {
+            let dir = project_dir.join(&namespace);
+            if !dir.exists() {
+                continue;
+            }
+            for entry in fs::read_dir(&dir)? {
                 let entry = entry?;
-                if entry.file_type()?.is_dir() {
-                    let dir_name = entry.file_name().to_string_lossy().to_string();
-                    if dir_name == Self::LEASE_DIR || dir_name == Self::METADATA_DIR {
-                        continue;
-                    }
-                    all_names.insert(dir_name);
+                if !entry.file_type()?.is_dir() {
+                    continue;
+                }
+                let dir_name = entry.file_name().to_string_lossy().to_string();
+                if namespace.is_empty()
+                    && (dir_name == Self::LEASE_DIR || dir_name == Self::METADATA_DIR)
+                {
+                    continue;
+                }
+                let name = if namespace.is_empty() {
+                    dir_name
+                } else {
+                    format!("{namespace}/{dir_name}")
+                };
+                if self.is_namespace_dir(&name, &hints)? {
+                    pending.push(name);
+                } else {
+                    all_names.insert(name);
                 }
             }
         }
@@ -625,19 +1768,62 @@ impl Godo {
         Ok(sorted_names)
     }
 
+    /// Fail when `sandbox_name` would nest inside, or hold, an existing sandbox.
+    ///
+    /// `bug` and `bug/1234` cannot coexist: Git cannot hold both `godo/bug` and `godo/bug/1234`
+    /// as branches, and one sandbox's directory would sit inside the other's.
+    fn check_nesting(&self, sandbox_name: &str) -> Result<()> {
+        for other in self.all_sandbox_names()? {
+            if naming::is_nested(sandbox_name, &other) || naming::is_nested(&other, sandbox_name) {
+                return Err(GodoError::SandboxError {
+                    name: sandbox_name.to_string(),
+                    message: format!("conflicts with existing sandbox {other}"),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove namespace directories left empty by removing the sandbox `name`.
+    fn prune_namespace_dirs(&self, name: &str) -> Result<()> {
+        if let Some(parent) = self.sandbox_path(name)?.parent() {
+            naming::prune_empty_dirs(parent, &self.project_dir()?);
+        }
+        Ok(())
+    }
+
     /// List all known sandboxes for the current project with their status.
     pub fn list(&self) -> Result<Vec<SandboxListEntry>> {
+        self.list_matching("", false)
+    }
+
+    /// List the sandboxes whose names start with `prefix`, such as `bug/`, with their status.
+    ///
+    /// Disk usage walks every sandbox tree, so it is only measured when `measure_usage` is set.
+    pub fn list_matching(
+        &self,
+        prefix: &str,
+        measure_usage: bool,
+    ) -> Result<Vec<SandboxListEntry>> {
         let sorted_names = self.all_sandbox_names()?;
         let project_dir = self.project_dir()?;
         let session_manager = SessionManager::new(&project_dir);
 
         let mut entries = Vec::new();
-        for name in &sorted_names {
+        for name in sorted_names.iter().filter(|name| name.starts_with(prefix)) {
             if let Some(status) = self.get_sandbox(name)? {
                 let connections = session_manager.active_connections(name)?;
+                // Usage is informational: a tree that changes while it is being walked is
+                // reported as unknown rather than failing the listing.
+                let disk_usage = if measure_usage && status.has_worktree_dir {
+                    usage::disk_usage(&self.sandbox_path(name)?).ok()
+                } else {
+                    None
+                };
                 entries.push(SandboxListEntry {
                     status,
                     active_connections: connections,
+                    disk_usage,
                 });
             }
         }
@@ -658,6 +1844,10 @@ impl Godo {
         if status.has_uncommitted_changes {
             blockers.push(RemovalBlocker::UncommittedChanges);
         }
+        // Detached sandboxes have no branch whose commits could be lost.
+        if status.detached && !status.has_branch {
+            return Ok(RemovalPlan { status, blockers });
+        }
         match status.merge_status {
             MergeStatus::Diverged => blockers.push(RemovalBlocker::UnmergedCommits),
             MergeStatus::Unknown => blockers.push(RemovalBlocker::MergeStatusUnknown),
@@ -705,17 +1895,47 @@ impl Godo {
         }
 
         self.remove_metadata(name)?;
-        Ok(())
+        self.prune_namespace_dirs(name)
     }
 
     /// Stage and commit all changes inside a sandbox.
     pub fn commit_all(&self, name: &str, message: &str) -> Result<()> {
-        let sandbox_path = self.require_worktree_path(name)?;
+        let sandbox_path = self.require_committable_path(name)?;
         git::add_all(&sandbox_path).map_err(|e| git_error(&e))?;
         git::commit(&sandbox_path, message).map_err(|e| git_error(&e))?;
         Ok(())
     }
 
+    /// Commit only the changes made inside a sandbox since its baseline.
+    ///
+    /// Uncommitted changes included when the sandbox was created stay in its working tree, and
+    /// staged if they were, and the baseline moves forward so later deltas start from this
+    /// commit. Without a baseline this is the same as [`Godo::commit_all`].
+    pub fn commit_delta(&self, name: &str, message: &str) -> Result<()> {
+        let sandbox_path = self.require_committable_path(name)?;
+        let Some(baseline) = self
+            .read_metadata(name)?
+            .and_then(|metadata| metadata.baseline_commit)
+        else {
+            return self.commit_all(name, message);
+        };
+
+        let snapshot = git::snapshot_tree(&sandbox_path).map_err(|e| git_error(&e))?;
+        git::commit_tree_changes(&sandbox_path, &baseline, &snapshot, message)
+            .map_err(|e| git_error(&e))?;
+
+        let head = git::rev_parse(&sandbox_path, "HEAD").map_err(|e| git_error(&e))?;
+        let baseline = git::commit_tree(&sandbox_path, &snapshot, &head, "godo baseline")
+            .map_err(|e| git_error(&e))?;
+        git::update_ref(&self.repo_dir, &self.baseline_ref(name), &baseline)
+            .map_err(|e| git_error(&e))?;
+        // Other sessions may have recorded usage since the read above, so only the baseline is
+        // changed, on a fresh read under the metadata lock.
+        self.update_metadata(name, |metadata| {
+            metadata.baseline_commit = Some(baseline);
+        })
+    }
+
     /// Clean one sandbox or all sandboxes by removing stale worktrees/branches
     /// when safe to do so.
     pub fn clean(&self, name: Option<&str>) -> Result<CleanupBatch> {
@@ -752,7 +1972,7 @@ impl Godo {
         let status = self.require_sandbox_status(name)?;
 
         let sandbox_path = self.sandbox_path(name)?;
-        let branch = branch_name(name);
+        let branch = self.branch_name(name);
 
         let mut worktree_removed = false;
         let mut branch_removed = false;
@@ -787,6 +2007,7 @@ impl Godo {
 
         if worktree_removed || branch_removed || directory_removed {
             self.remove_metadata(name)?;
+            self.prune_namespace_dirs(name)?;
         }
 
         Ok(CleanupReport {
@@ -803,7 +2024,7 @@ impl Godo {
         let status = self.require_sandbox_status(name)?;
 
         let sandbox_path = self.sandbox_path(name)?;
-        let branch = branch_name(name);
+        let branch = self.branch_name(name);
 
         if status.has_worktree {
             git::remove_worktree(&self.repo_dir, &sandbox_path, true).map_err(|e| git_error(&e))?;
@@ -817,10 +2038,23 @@ impl Godo {
             git::delete_branch(&self.repo_dir, &branch, true).map_err(|e| git_error(&e))?;
         }
         self.remove_metadata(name)?;
-        Ok(())
+        self.prune_namespace_dirs(name)
     }
 }
 
+/// Name of the user running godo, if the environment says.
+fn current_user() -> Option<String> {
+    env::var("USER").or_else(|_| env::var("USERNAME")).ok()
+}
+
+/// Current time as seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
 /// Ensure the primary godo directory hierarchy exists.
 fn ensure_godo_directory(godo_dir: &Path) -> Result<()> {
     // Create main godo directory
@@ -950,6 +2184,7 @@ mod tests {
     fn sandbox_component_status_reports_components() {
         let sandbox = SandboxStatus {
             name: "example".to_string(),
+            branch: "godo/example".to_string(),
             has_branch: true,
             has_worktree: true,
             has_worktree_dir: true,
@@ -961,6 +2196,7 @@ mod tests {
             merge_status: MergeStatus::Unknown,
             unmerged_commits: Vec::new(),
             is_dangling: false,
+            detached: false,
         };
 
         assert_eq!(
@@ -1000,7 +2236,14 @@ mod tests {
         let manager = Godo::new(godo_dir, Some(repo_dir.clone())).unwrap();
 
         let sandbox_path = manager.sandbox_path("box").unwrap();
-        git::create_worktree(&repo_dir, &sandbox_path, &branch_name("box")).unwrap();
+        git::create_worktree_at(
+            &repo_dir,
+            &sandbox_path,
+            &manager.branch_name("box"),
+            None,
+            true,
+        )
+        .unwrap();
 
         let result = manager.resolve_base_commit("box", None);
         assert!(matches!(result, Err(GodoError::BaseError { .. })));
@@ -1016,7 +2259,14 @@ mod tests {
         let manager = Godo::new(godo_dir, Some(repo_dir.clone())).unwrap();
 
         let sandbox_path = manager.sandbox_path("box").unwrap();
-        git::create_worktree(&repo_dir, &sandbox_path, &branch_name("box")).unwrap();
+        git::create_worktree_at(
+            &repo_dir,
+            &sandbox_path,
+            &manager.branch_name("box"),
+            None,
+            true,
+        )
+        .unwrap();
 
         let expected = git::rev_parse(&repo_dir, "HEAD").unwrap();
         let resolved = manager.resolve_base_commit("box", Some("HEAD")).unwrap();
@@ -1033,12 +2283,22 @@ mod tests {
         let manager = Godo::new(godo_dir, Some(repo_dir.clone())).unwrap();
 
         let sandbox_path = manager.sandbox_path("box").unwrap();
-        git::create_worktree(&repo_dir, &sandbox_path, &branch_name("box")).unwrap();
+        git::create_worktree_at(
+            &repo_dir,
+            &sandbox_path,
+            &manager.branch_name("box"),
+            None,
+            true,
+        )
+        .unwrap();
 
         let metadata = SandboxMetadata {
             base_commit: "deadbeef".to_string(),
             base_ref: None,
             created_at: 1_700_000_000,
+            baseline_commit: None,
+            detached: false,
+            ..SandboxMetadata::default()
         };
         manager
             .metadata_store()
@@ -1053,7 +2313,7 @@ mod tests {
         let target = resolved.fallback_target.as_deref().unwrap();
 
         // Verify the commit matches what merge-base would return for that target
-        let expected = git::merge_base(&repo_dir, &branch_name("box"), target).unwrap();
+        let expected = git::merge_base(&repo_dir, &manager.branch_name("box"), target).unwrap();
         assert_eq!(resolved.commit, expected);
 
         // Verify the target is one of the expected candidates
@@ -1083,12 +2343,22 @@ mod tests {
         let manager = Godo::new(godo_dir, Some(repo_dir.clone())).unwrap();
 
         let sandbox_path = manager.sandbox_path("box").unwrap();
-        git::create_worktree(&repo_dir, &sandbox_path, &branch_name("box")).unwrap();
+        git::create_worktree_at(
+            &repo_dir,
+            &sandbox_path,
+            &manager.branch_name("box"),
+            None,
+            true,
+        )
+        .unwrap();
 
         let metadata = SandboxMetadata {
             base_commit: "deadbeef".to_string(),
             base_ref: Some("origin/dev".to_string()),
             created_at: 1_700_000_000,
+            baseline_commit: None,
+            detached: false,
+            ..SandboxMetadata::default()
         };
         manager
             .metadata_store()
@@ -1127,18 +2397,20 @@ mod tests {
     fn detects_missing_worktree_directory() {
         let tmp = tempdir().unwrap();
         let repo_dir = tmp.path().join("repo");
-        fs::create_dir(&repo_dir).unwrap();
-        Command::new("git")
-            .arg("init")
-            .current_dir(&repo_dir)
-            .status()
-            .unwrap();
+        init_repo(&repo_dir);
 
         let godo_dir = tmp.path().join("godo");
         let manager = Godo::new(godo_dir, Some(repo_dir.clone())).unwrap();
 
         let sandbox_path = manager.sandbox_path("box").unwrap();
-        git::create_worktree(&repo_dir, &sandbox_path, &branch_name("box")).unwrap();
+        git::create_worktree_at(
+            &repo_dir,
+            &sandbox_path,
+            &manager.branch_name("box"),
+            None,
+            true,
+        )
+        .unwrap();
 
         fs::remove_dir_all(&sandbox_path).unwrap();
 
@@ -1147,6 +2419,34 @@ mod tests {
         assert!(!sandbox.is_live());
     }
 
+    #[test]
+    fn prepare_sandbox_requires_a_commit() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        fs::create_dir(&repo_dir).unwrap();
+        run_git(&repo_dir, &["init"]);
+
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir)).unwrap();
+        let result = godo.prepare_sandbox(
+            "box",
+            PrepareSandboxOptions {
+                uncommitted_policy: UncommittedPolicy::Include,
+                excludes: Vec::new(),
+                base: None,
+                setup_hooks: Vec::new(),
+                require_cow: false,
+                sparse_paths: Vec::new(),
+                detached: false,
+                command: Vec::new(),
+            },
+        );
+        let Err(GodoError::BaseError { message, .. }) = result else {
+            panic!("expected a base error");
+        };
+        assert!(message.contains("no commits"), "{message}");
+        assert!(godo.sandbox_status("box").unwrap().is_none());
+    }
+
     #[test]
     fn test_project_name() {
         let test_cases = vec![
@@ -1184,6 +2484,8 @@ mod tests {
             "my-feature-branch",
             "bug_fix_123",
             "RELEASE-2024",
+            "bug/1234",
+            "alice/refactor/step-2",
         ];
 
         let invalid_names = vec![
@@ -1191,7 +2493,9 @@ mod tests {
             "test space",    // contains space
             "test.dot",      // contains dot
             "test@symbol",   // contains @
-            "test/slash",    // contains /
+            "/leading",      // empty namespace
+            "trailing/",     // empty name
+            "double//slash", // empty component
             r"test\back",    // contains backslash
             "test:colon",    // contains colon
             "test*star",     // contains asterisk
@@ -1214,25 +2518,6 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_branch_name() {
-        let test_cases = vec![
-            ("test", "godo/test"),
-            ("feature-123", "godo/feature-123"),
-            ("my_sandbox", "godo/my_sandbox"),
-            ("bugfix", "godo/bugfix"),
-            ("RELEASE-2024", "godo/RELEASE-2024"),
-        ];
-
-        for (sandbox, expected) in test_cases {
-            assert_eq!(
-                branch_name(sandbox),
-                expected,
-                "Failed for sandbox name: '{sandbox}'"
-            );
-        }
-    }
-
     #[test]
     fn test_sandbox_and_project_paths() {
         use tempfile::TempDir;
@@ -1242,19 +2527,1097 @@ mod tests {
         let repo_dir = PathBuf::from("/home/user/projects/my-project");
 
         // Create a Godo instance
-        let godo = Godo::new(godo_dir.clone(), Some(repo_dir)).unwrap();
+        let godo = Godo::new(godo_dir.clone(), Some(repo_dir.clone())).unwrap();
 
         // Test project_dir method
         let project_dir = godo.project_dir().unwrap();
-        let canonical_godo_dir = fs::canonicalize(&godo_dir).unwrap_or(godo_dir);
-        assert_eq!(project_dir, canonical_godo_dir.join("my-project"));
+        let canonical_godo_dir = fs::canonicalize(&godo_dir).unwrap_or(godo_dir.clone());
+        let project_id = registry::project_id("my-project", &repo_dir);
+        assert!(project_id.starts_with("my-project-"));
+        assert_eq!(project_dir, canonical_godo_dir.join(&project_id));
 
         // Test sandbox_path method
         let sandbox_path = godo.sandbox_path("test-sandbox").unwrap();
         assert_eq!(
             sandbox_path,
-            canonical_godo_dir.join("my-project").join("test-sandbox")
+            canonical_godo_dir.join(&project_id).join("test-sandbox")
+        );
+
+        // Another checkout with the same directory name gets its own project directory
+        let other = Godo::new(godo_dir, Some(PathBuf::from("/srv/my-project"))).unwrap();
+        assert_ne!(other.project_dir().unwrap(), project_dir);
+    }
+
+    #[test]
+    fn legacy_project_dirs_are_migrated() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("app");
+        init_repo(&repo_dir);
+        let repo_dir = fs::canonicalize(&repo_dir).unwrap();
+        let godo_dir = fs::canonicalize(tmp.path()).unwrap().join("godo");
+
+        // Lay out a sandbox the way older versions did, in `<godo dir>/<project name>`
+        let legacy_dir = godo_dir.join("app");
+        let legacy_store = SandboxMetadataStore::new(&legacy_dir);
+        let metadata = SandboxMetadata {
+            base_commit: git::rev_parse(&repo_dir, "HEAD").unwrap(),
+            base_ref: Some("main".to_string()),
+            created_at: 1_700_000_000,
+            baseline_commit: None,
+            detached: false,
+            ..SandboxMetadata::default()
+        };
+        legacy_store.write("bug/1", &metadata).unwrap();
+        fs::create_dir_all(legacy_dir.join("bug")).unwrap();
+        run_git(
+            &repo_dir,
+            &[
+                "worktree",
+                "add",
+                "-b",
+                "godo/bug/1",
+                legacy_dir.join("bug/1").to_str().unwrap(),
+            ],
         );
+        fs::create_dir_all(legacy_dir.join(Godo::LEASE_DIR)).unwrap();
+
+        // Opening the project leaves the sandbox where it is until it is repaired.
+        let godo = Godo::new(godo_dir.clone(), Some(repo_dir.clone())).unwrap();
+        assert!(legacy_dir.join("bug/1").exists());
+        assert!(godo.needs_repair().unwrap());
+        let report = godo.repair().unwrap();
+        assert_eq!(report.adopted, vec!["bug/1".to_string()]);
+        assert!(!godo.needs_repair().unwrap());
+        let sandbox_path = godo.sandbox_path("bug/1").unwrap();
+        assert!(sandbox_path.join("README.md").exists());
+        assert!(!legacy_dir.exists());
+
+        let worktrees = git::list_worktrees(&repo_dir).unwrap();
+        assert!(worktrees.iter().any(|w| w.path == sandbox_path));
+        assert_eq!(
+            git::rev_parse(&sandbox_path, "HEAD").unwrap(),
+            metadata.base_commit
+        );
+        assert_eq!(
+            godo.metadata_store().unwrap().read("bug/1").unwrap(),
+            Some(metadata)
+        );
+
+        let projects = ProjectRegistry::new(&godo_dir).projects().unwrap();
+        assert_eq!(
+            projects.get(&godo.project_id().unwrap()),
+            Some(&ProjectEntry {
+                repo_dir,
+                project_dir: Some(godo.project_dir().unwrap()),
+            })
+        );
+        assert!(godo.get_sandbox("bug/1").unwrap().is_some());
+    }
+
+    #[test]
+    fn prepare_sandbox_applies_nested_exclude_globs() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        init_repo(&repo_dir);
+
+        fs::create_dir_all(repo_dir.join("crates/foo/target")).unwrap();
+        fs::create_dir_all(repo_dir.join("crates/foo/src")).unwrap();
+        fs::create_dir_all(repo_dir.join("web/app/node_modules/pkg")).unwrap();
+        fs::write(repo_dir.join("crates/foo/target/out.bin"), "out").unwrap();
+        fs::write(repo_dir.join("crates/foo/src/lib.rs"), "lib").unwrap();
+        fs::write(repo_dir.join("web/app/node_modules/pkg/index.js"), "js").unwrap();
+        fs::write(repo_dir.join("web/app/main.js"), "main").unwrap();
+        fs::write(repo_dir.join("build.log"), "log").unwrap();
+        fs::write(repo_dir.join("web/debug.log"), "log").unwrap();
+
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir)).unwrap();
+        let plan = godo
+            .prepare_sandbox(
+                "box",
+                PrepareSandboxOptions {
+                    uncommitted_policy: UncommittedPolicy::Include,
+                    excludes: vec![
+                        "**/node_modules".to_string(),
+                        "crates/*/target".to_string(),
+                        "*.log".to_string(),
+                    ],
+                    base: None,
+                    setup_hooks: Vec::new(),
+                    require_cow: false,
+                    sparse_paths: Vec::new(),
+                    detached: false,
+                    command: Vec::new(),
+                },
+            )
+            .unwrap();
+
+        let sandbox = plan.session.path.clone();
+        assert!(sandbox.join("crates/foo/src/lib.rs").exists());
+        assert!(sandbox.join("web/app/main.js").exists());
+        assert!(!sandbox.join("crates/foo/target").exists());
+        assert!(!sandbox.join("web/app/node_modules").exists());
+        assert!(!sandbox.join("build.log").exists());
+        assert!(!sandbox.join("web/debug.log").exists());
+        let _ = plan.session.release().unwrap();
+    }
+
+    #[test]
+    fn prepare_sandbox_clones_once_into_populated_index() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        init_repo(&repo_dir);
+        fs::create_dir_all(repo_dir.join("vendor")).unwrap();
+        fs::write(repo_dir.join("vendor/lib.c"), "tracked").unwrap();
+        fs::write(repo_dir.join(".gitignore"), "*.o\n").unwrap();
+        run_git(&repo_dir, &["add", "vendor", ".gitignore"]);
+        run_git(&repo_dir, &["commit", "-m", "Vendor"]);
+        fs::write(repo_dir.join("README.md"), "edited").unwrap();
+        fs::write(repo_dir.join("main.o"), "object").unwrap();
+
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir)).unwrap();
+        let create = |name: &str, policy| {
+            let plan = godo
+                .prepare_sandbox(
+                    name,
+                    PrepareSandboxOptions {
+                        uncommitted_policy: policy,
+                        excludes: vec!["vendor/".to_string()],
+                        base: None,
+                        setup_hooks: Vec::new(),
+                        require_cow: false,
+                        sparse_paths: Vec::new(),
+                        detached: false,
+                        command: Vec::new(),
+                    },
+                )
+                .unwrap();
+            let path = plan.session.path.clone();
+            let _ = plan.session.release().unwrap();
+            path
+        };
+
+        // Included changes show up as modifications; excluded tracked files are not deletions.
+        let included = create("included", UncommittedPolicy::Include);
+        let status = Command::new("git")
+            .current_dir(&included)
+            .args(["status", "--porcelain"])
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&status.stdout), " M README.md\n");
+        assert_eq!(
+            fs::read_to_string(included.join("vendor/lib.c")).unwrap(),
+            "tracked"
+        );
+        assert!(included.join("main.o").exists());
+
+        // A clean start checks out HEAD and carries over only ignored files.
+        let clean = create("clean", UncommittedPolicy::Clean);
+        assert_eq!(fs::read_to_string(clean.join("README.md")).unwrap(), "base");
+        assert!(clean.join("main.o").exists());
+        assert!(!git::has_uncommitted_changes(&clean).unwrap());
+    }
+
+    #[test]
+    fn prepare_sandbox_replicates_source_index() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        init_repo(&repo_dir);
+        fs::write(repo_dir.join("notes.txt"), "notes").unwrap();
+        run_git(&repo_dir, &["add", "notes.txt"]);
+        run_git(&repo_dir, &["commit", "-m", "Notes"]);
+        fs::write(repo_dir.join("README.md"), "staged").unwrap();
+        fs::write(repo_dir.join("added.txt"), "added").unwrap();
+        run_git(&repo_dir, &["add", "README.md", "added.txt"]);
+        run_git(&repo_dir, &["rm", "--quiet", "notes.txt"]);
+        fs::write(repo_dir.join("README.md"), "unstaged").unwrap();
+
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir.clone())).unwrap();
+        let plan = godo
+            .prepare_sandbox(
+                "staged",
+                PrepareSandboxOptions {
+                    uncommitted_policy: UncommittedPolicy::Include,
+                    excludes: Vec::new(),
+                    base: None,
+                    setup_hooks: Vec::new(),
+                    require_cow: false,
+                    sparse_paths: Vec::new(),
+                    detached: false,
+                    command: Vec::new(),
+                },
+            )
+            .unwrap();
+        let sandbox = plan.session.path.clone();
+        let _ = plan.session.release().unwrap();
+
+        let git_output = |dir: &Path, args: &[&str]| {
+            let output = Command::new("git")
+                .current_dir(dir)
+                .args(args)
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+        assert_eq!(
+            git_output(&sandbox, &["status", "--porcelain"]),
+            "MM README.md\nA  added.txt\nD  notes.txt\n"
+        );
+        for args in [&["diff", "--cached"][..], &["diff"][..]] {
+            assert_eq!(git_output(&sandbox, args), git_output(&repo_dir, args));
+        }
+    }
+
+    #[test]
+    fn baseline_separates_included_changes_from_sandbox_work() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        init_repo(&repo_dir);
+        fs::write(repo_dir.join("README.md"), "included").unwrap();
+        fs::write(repo_dir.join("draft.txt"), "draft").unwrap();
+
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir.clone())).unwrap();
+        let plan = godo
+            .prepare_sandbox(
+                "box",
+                PrepareSandboxOptions {
+                    uncommitted_policy: UncommittedPolicy::Include,
+                    excludes: Vec::new(),
+                    base: None,
+                    setup_hooks: Vec::new(),
+                    require_cow: false,
+                    sparse_paths: Vec::new(),
+                    detached: false,
+                    command: Vec::new(),
+                },
+            )
+            .unwrap();
+        let sandbox = plan.session.path.clone();
+        let _ = plan.session.release().unwrap();
+        let baseline_ref = godo.baseline_ref("box");
+        assert!(
+            godo.read_metadata("box")
+                .unwrap()
+                .unwrap()
+                .baseline_commit
+                .is_some()
+        );
+
+        let git_output = |args: &[&str]| {
+            let output = Command::new("git")
+                .current_dir(&sandbox)
+                .args(args)
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+
+        // Work done inside the sandbox: one new file.
+        fs::write(sandbox.join("result.txt"), "result").unwrap();
+        let plan = godo.delta_diff_plan("box").unwrap();
+        let target = plan.target_tree.unwrap();
+        assert_eq!(
+            git_output(&["diff", "--name-status", &plan.base_commit, &target]),
+            "A\tresult.txt\n"
+        );
+
+        // Only the sandbox's work is committed; the included changes stay uncommitted.
+        godo.commit_delta("box", "Add result").unwrap();
+        assert_eq!(
+            git_output(&["show", "--name-status", "--format=", "HEAD"]),
+            "A\tresult.txt\n"
+        );
+        assert_eq!(
+            git_output(&["status", "--porcelain"]),
+            " M README.md\n?? draft.txt\n"
+        );
+
+        // The baseline moves forward, so later work is committed separately.
+        fs::write(sandbox.join("result.txt"), "more").unwrap();
+        let plan = godo.delta_diff_plan("box").unwrap();
+        assert_eq!(
+            git_output(&[
+                "diff",
+                "--name-status",
+                &plan.base_commit,
+                &plan.target_tree.unwrap()
+            ]),
+            "M\tresult.txt\n"
+        );
+        godo.commit_delta("box", "More result").unwrap();
+        assert_eq!(
+            fs::read_to_string(sandbox.join("README.md")).unwrap(),
+            "included"
+        );
+
+        godo.remove_sandbox_force("box").unwrap();
+        let ref_exists = Command::new("git")
+            .current_dir(&repo_dir)
+            .args(["rev-parse", "--verify", "--quiet", &baseline_ref])
+            .status()
+            .unwrap()
+            .success();
+        assert!(
+            !ref_exists,
+            "baseline ref should be removed with the sandbox"
+        );
+    }
+
+    #[test]
+    fn commit_delta_keeps_included_changes_staged() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        init_repo(&repo_dir);
+        fs::write(repo_dir.join("README.md"), "staged").unwrap();
+        run_git(&repo_dir, &["add", "README.md"]);
+
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir)).unwrap();
+        let plan = godo
+            .prepare_sandbox(
+                "box",
+                PrepareSandboxOptions {
+                    uncommitted_policy: UncommittedPolicy::Include,
+                    excludes: Vec::new(),
+                    base: None,
+                    setup_hooks: Vec::new(),
+                    require_cow: false,
+                    sparse_paths: Vec::new(),
+                    detached: false,
+                    command: Vec::new(),
+                },
+            )
+            .unwrap();
+        let sandbox = plan.session.path.clone();
+        let _ = plan.session.release().unwrap();
+        let status = || {
+            let output = Command::new("git")
+                .current_dir(&sandbox)
+                .args(["status", "--porcelain"])
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+        assert_eq!(status(), "M  README.md\n");
+
+        fs::write(sandbox.join("result.txt"), "result").unwrap();
+        godo.commit_delta("box", "Add result").unwrap();
+        assert_eq!(status(), "M  README.md\n");
+    }
+
+    #[test]
+    fn detached_sandbox_has_no_branch_and_cleans_up_without_merge_status() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        init_repo(&repo_dir);
+        fs::write(repo_dir.join("README.md"), "edited").unwrap();
+
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir.clone())).unwrap();
+        let plan = godo
+            .prepare_sandbox(
+                "scratch",
+                PrepareSandboxOptions {
+                    uncommitted_policy: UncommittedPolicy::Include,
+                    excludes: Vec::new(),
+                    base: None,
+                    setup_hooks: Vec::new(),
+                    require_cow: false,
+                    sparse_paths: Vec::new(),
+                    detached: true,
+                    command: Vec::new(),
+                },
+            )
+            .unwrap();
+        let sandbox = plan.session.path.clone();
+        let _ = plan.session.release().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(sandbox.join("README.md")).unwrap(),
+            "edited"
+        );
+        assert!(!git::has_branch(&repo_dir, &godo.branch_name("scratch")).unwrap());
+        let status = godo.sandbox_status("scratch").unwrap().unwrap();
+        assert!(status.detached && status.is_live() && !status.is_dangling);
+        assert!(godo.commit_all("scratch", "nope").is_err());
+
+        // Only uncommitted changes keep a detached sandbox around.
+        let plan = godo.removal_plan("scratch").unwrap();
+        assert_eq!(plan.blockers, vec![RemovalBlocker::UncommittedChanges]);
+        fs::write(sandbox.join("README.md"), "base").unwrap();
+        assert!(godo.removal_plan("scratch").unwrap().blockers.is_empty());
+
+        let batch = godo.clean(Some("scratch")).unwrap();
+        assert!(batch.reports[0].worktree_removed);
+        assert!(godo.sandbox_status("scratch").unwrap().is_none());
+    }
+
+    #[test]
+    fn prepare_sandbox_reports_copy_strategy() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        init_repo(&repo_dir);
+        fs::write(repo_dir.join("notes.txt"), "untracked").unwrap();
+
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir)).unwrap();
+        let supported = godo.supports_cow().unwrap() == Some(true);
+        let result = godo.prepare_sandbox(
+            "box",
+            PrepareSandboxOptions {
+                uncommitted_policy: UncommittedPolicy::Include,
+                excludes: Vec::new(),
+                base: None,
+                setup_hooks: Vec::new(),
+                require_cow: true,
+                sparse_paths: Vec::new(),
+                detached: false,
+                command: Vec::new(),
+            },
+        );
+
+        if supported {
+            let plan = result.unwrap();
+            let stats = &plan.clone_stats;
+            assert!(stats.copy_on_write);
+            assert_eq!((stats.copied_files, stats.bytes_written), (0, 0));
+            assert_eq!(stats.reflinked_files, 2);
+        } else {
+            assert!(matches!(
+                result,
+                Err(GodoError::CopyOnWriteUnavailable { .. })
+            ));
+            assert!(!godo.sandbox_path("box").unwrap().exists());
+        }
+    }
+
+    #[test]
+    fn prepare_sandbox_from_base_revision() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        init_repo(&repo_dir);
+        run_git(&repo_dir, &["tag", "v1"]);
+        let tagged = git::rev_parse(&repo_dir, "v1").unwrap();
+
+        fs::write(repo_dir.join("README.md"), "next").unwrap();
+        fs::write(repo_dir.join(".gitignore"), "target/\n").unwrap();
+        run_git(&repo_dir, &["add", "README.md", ".gitignore"]);
+        run_git(&repo_dir, &["commit", "-m", "Next"]);
+        fs::create_dir_all(repo_dir.join("target")).unwrap();
+        fs::write(repo_dir.join("target/cache"), "cache").unwrap();
+        fs::write(repo_dir.join("scratch.txt"), "untracked").unwrap();
+
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir)).unwrap();
+        let plan = godo
+            .prepare_sandbox(
+                "box",
+                PrepareSandboxOptions {
+                    uncommitted_policy: UncommittedPolicy::Abort,
+                    excludes: Vec::new(),
+                    base: Some("v1".to_string()),
+                    setup_hooks: Vec::new(),
+                    require_cow: false,
+                    sparse_paths: Vec::new(),
+                    detached: false,
+                    command: Vec::new(),
+                },
+            )
+            .unwrap();
+
+        let sandbox = plan.session.path.clone();
+        assert_eq!(
+            fs::read_to_string(sandbox.join("README.md")).unwrap(),
+            "base"
+        );
+        assert!(sandbox.join("target/cache").exists());
+        assert!(!sandbox.join("scratch.txt").exists());
+        assert!(!sandbox.join(".gitignore").exists());
+
+        let metadata = godo.metadata_store().unwrap().read("box").unwrap().unwrap();
+        assert_eq!(metadata.base_commit, tagged);
+        assert_eq!(metadata.base_ref.as_deref(), Some("v1"));
+        let _ = plan.session.release().unwrap();
+    }
+
+    #[test]
+    fn prepare_sandbox_runs_setup_hooks_and_rolls_back_on_failure() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        init_repo(&repo_dir);
+
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir)).unwrap();
+        let options = |setup_hooks: &[&str]| PrepareSandboxOptions {
+            uncommitted_policy: UncommittedPolicy::Include,
+            excludes: Vec::new(),
+            base: None,
+            setup_hooks: setup_hooks.iter().map(|hook| hook.to_string()).collect(),
+            require_cow: false,
+            sparse_paths: Vec::new(),
+            detached: false,
+            command: Vec::new(),
+        };
+
+        let mut lines = Vec::new();
+        let plan = godo
+            .prepare_sandbox_with_events(
+                "ok",
+                options(&["echo ready > hook.txt", "echo done"]),
+                &mut |event| {
+                    if let PrepareEvent::HookOutput { line, .. } = event {
+                        lines.push(line);
+                    }
+                },
+            )
+            .unwrap();
+        assert_eq!(plan.hooks_run, vec!["echo ready > hook.txt", "echo done"]);
+        assert_eq!(lines, vec!["done"]);
+        assert!(plan.session.path.join("hook.txt").exists());
+        let _ = plan.session.release().unwrap();
+
+        let err = godo
+            .prepare_sandbox("bad", options(&["echo oops >&2; exit 3"]))
+            .unwrap_err();
+        match err {
+            GodoError::HookFailed { status, output, .. } => {
+                assert!(status.contains('3'));
+                assert_eq!(output, "oops\n");
+            }
+            other => panic!("unexpected error: {other}"),
+        }
+        assert!(godo.sandbox_status("bad").unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn prepare_sandbox_rolls_back_failed_clone() {
+        use std::os::unix::net::UnixListener;
+
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        init_repo(&repo_dir);
+        // Sockets cannot be copied, so cloning the working tree fails partway through.
+        let _listener = UnixListener::bind(repo_dir.join("agent.sock")).unwrap();
+
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir.clone())).unwrap();
+        let result = godo.prepare_sandbox(
+            "box",
+            PrepareSandboxOptions {
+                uncommitted_policy: UncommittedPolicy::Include,
+                excludes: Vec::new(),
+                base: None,
+                setup_hooks: Vec::new(),
+                require_cow: false,
+                sparse_paths: Vec::new(),
+                detached: false,
+                command: Vec::new(),
+            },
+        );
+        assert!(matches!(result, Err(GodoError::OperationError(_))));
+
+        assert!(godo.list().unwrap().is_empty());
+        assert!(!godo.sandbox_path("box").unwrap().exists());
+        assert!(!git::has_branch(&repo_dir, "godo/box").unwrap());
+        assert_eq!(git::list_worktrees(&repo_dir).unwrap().len(), 1);
+        assert!(
+            godo.metadata_store()
+                .unwrap()
+                .read("box")
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn fork_sandbox_clones_source_sandbox_state() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        init_repo(&repo_dir);
+        fs::write(repo_dir.join(".gitignore"), "target/\n").unwrap();
+        run_git(&repo_dir, &["add", ".gitignore"]);
+        run_git(&repo_dir, &["commit", "-m", "Ignore target"]);
+        let base = git::rev_parse(&repo_dir, "HEAD").unwrap();
+
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir)).unwrap();
+        let plan = godo
+            .prepare_sandbox(
+                "warm",
+                PrepareSandboxOptions {
+                    uncommitted_policy: UncommittedPolicy::Include,
+                    excludes: vec!["*.log".to_string()],
+                    base: None,
+                    setup_hooks: Vec::new(),
+                    require_cow: false,
+                    sparse_paths: Vec::new(),
+                    detached: false,
+                    command: vec!["cargo".to_string(), "build".to_string()],
+                },
+            )
+            .unwrap();
+        let warm = plan.session.path.clone();
+        let _ = plan.session.release().unwrap();
+        fs::write(warm.join("lib.rs"), "committed").unwrap();
+        run_git(&warm, &["add", "lib.rs"]);
+        run_git(&warm, &["commit", "-m", "Work"]);
+        fs::write(warm.join("README.md"), "edited").unwrap();
+        fs::create_dir_all(warm.join("target")).unwrap();
+        fs::write(warm.join("target/artifact"), "built").unwrap();
+
+        godo.fork_sandbox("warm", "experiment", &mut |_| {})
+            .unwrap();
+
+        let fork = godo.sandbox_path("experiment").unwrap();
+        assert!(
+            godo.sandbox_status("experiment")
+                .unwrap()
+                .unwrap()
+                .is_live()
+        );
+        assert_eq!(
+            git::rev_parse(&fork, "HEAD").unwrap(),
+            git::rev_parse(&warm, "HEAD").unwrap()
+        );
+        assert_eq!(
+            fs::read_to_string(fork.join("target/artifact")).unwrap(),
+            "built"
+        );
+        let status = Command::new("git")
+            .current_dir(&fork)
+            .args(["status", "--porcelain"])
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&status.stdout), " M README.md\n");
+        let metadata = godo
+            .metadata_store()
+            .unwrap()
+            .read("experiment")
+            .unwrap()
+            .unwrap();
+        assert_eq!(metadata.base_commit, base);
+        assert_eq!(metadata.command, vec!["cargo", "build"]);
+        assert_eq!(metadata.excludes, vec!["*.log"]);
+        assert_eq!(
+            metadata.uncommitted_policy,
+            Some(UncommittedPolicy::Include)
+        );
+        assert_eq!(metadata.last_exit_code, None);
+        // The lease held on the source during the copy is released afterwards.
+        assert_eq!(
+            SessionManager::new(&godo.project_dir().unwrap())
+                .active_connections("warm")
+                .unwrap(),
+            0
+        );
+
+        let result = godo.fork_sandbox("warm", "experiment", &mut |_| {});
+        assert!(matches!(result, Err(GodoError::SandboxError { .. })));
+        let result = godo.fork_sandbox("missing", "other", &mut |_| {});
+        assert!(matches!(result, Err(GodoError::SandboxError { .. })));
+        assert!(!godo.sandbox_path("other").unwrap().exists());
+    }
+
+    #[test]
+    fn prepare_sandbox_attaches_submodules() {
+        let tmp = tempdir().unwrap();
+        let lib_dir = tmp.path().join("lib");
+        init_repo(&lib_dir);
+        let repo_dir = tmp.path().join("repo");
+        init_repo(&repo_dir);
+        run_git(
+            &repo_dir,
+            &[
+                "-c",
+                "protocol.file.allow=always",
+                "submodule",
+                "add",
+                lib_dir.to_str().unwrap(),
+                "vendor/lib",
+            ],
+        );
+        run_git(&repo_dir, &["commit", "-m", "Add submodule"]);
+        let source_lib = repo_dir.join("vendor/lib");
+        fs::write(source_lib.join("README.md"), "base\nedited\n").unwrap();
+
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir)).unwrap();
+        let create = |name: &str, policy| {
+            let plan = godo
+                .prepare_sandbox(
+                    name,
+                    PrepareSandboxOptions {
+                        uncommitted_policy: policy,
+                        excludes: Vec::new(),
+                        base: None,
+                        setup_hooks: Vec::new(),
+                        require_cow: false,
+                        sparse_paths: Vec::new(),
+                        detached: false,
+                        command: Vec::new(),
+                    },
+                )
+                .unwrap();
+            let path = plan.session.path.clone();
+            let _ = plan.session.release().unwrap();
+            path
+        };
+        let porcelain = |dir: &Path| {
+            let output = Command::new("git")
+                .current_dir(dir)
+                .args(["status", "--porcelain"])
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+
+        // The submodule is a worktree of the source submodule, with its changes carried over.
+        let included = create("included", UncommittedPolicy::Include);
+        let sandbox_lib = included.join("vendor/lib");
+        assert!(sandbox_lib.join(".git").is_file());
+        assert_eq!(porcelain(&sandbox_lib), " M README.md\n");
+        assert_eq!(porcelain(&included), " M vendor/lib\n");
+        let status = godo.sandbox_status("included").unwrap().unwrap();
+        assert_eq!(status.diff_stats.unwrap().insertions, 2);
+
+        // A clean sandbox checks the submodule out at the recorded commit.
+        let clean = create("clean", UncommittedPolicy::Clean);
+        assert_eq!(
+            fs::read_to_string(clean.join("vendor/lib/README.md")).unwrap(),
+            "base"
+        );
+        assert_eq!(porcelain(&clean), "");
+
+        for name in ["included", "clean"] {
+            let plan = godo.removal_plan(name).unwrap();
+            let outcome = godo.remove(&plan, &RemovalOptions::force()).unwrap();
+            assert!(matches!(outcome, RemovalOutcome::Removed));
+        }
+        let worktrees = Command::new("git")
+            .current_dir(&source_lib)
+            .args(["worktree", "list"])
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&worktrees.stdout).lines().count(),
+            1
+        );
+    }
+
+    #[test]
+    fn prepare_sandbox_restricts_sparse_sandboxes() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        init_repo(&repo_dir);
+        for (path, contents) in [
+            ("crates/foo/src/lib.rs", "foo"),
+            ("crates/bar/src/lib.rs", "bar"),
+            ("crates/Cargo.toml", "workspace"),
+            ("docs/guide.md", "guide"),
+        ] {
+            let path = repo_dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        run_git(&repo_dir, &["add", "."]);
+        run_git(&repo_dir, &["commit", "-m", "Layout"]);
+        fs::write(repo_dir.join("crates/foo/src/lib.rs"), "edited").unwrap();
+
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir)).unwrap();
+        let create = |name: &str, policy| {
+            let plan = godo
+                .prepare_sandbox(
+                    name,
+                    PrepareSandboxOptions {
+                        uncommitted_policy: policy,
+                        excludes: Vec::new(),
+                        base: None,
+                        setup_hooks: Vec::new(),
+                        require_cow: false,
+                        sparse_paths: vec!["crates/foo".to_string()],
+                        detached: false,
+                        command: Vec::new(),
+                    },
+                )
+                .unwrap();
+            let path = plan.session.path.clone();
+            let _ = plan.session.release().unwrap();
+            path
+        };
+        let porcelain = |dir: &Path| {
+            let output = Command::new("git")
+                .current_dir(dir)
+                .args(["status", "--porcelain"])
+                .output()
+                .unwrap();
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+        let assert_cone = |dir: &Path| {
+            assert!(dir.join("README.md").exists());
+            assert!(dir.join("crates/Cargo.toml").exists());
+            assert!(dir.join("crates/foo/src/lib.rs").exists());
+            assert!(!dir.join("crates/bar").exists());
+            assert!(!dir.join("docs").exists());
+        };
+
+        let included = create("included", UncommittedPolicy::Include);
+        assert_cone(&included);
+        assert_eq!(porcelain(&included), " M crates/foo/src/lib.rs\n");
+
+        let clean = create("clean", UncommittedPolicy::Clean);
+        assert_cone(&clean);
+        assert_eq!(porcelain(&clean), "");
+
+        godo.fork_sandbox("included", "fork", &mut |_| {}).unwrap();
+        let fork = godo.sandbox_path("fork").unwrap();
+        assert_cone(&fork);
+        assert_eq!(porcelain(&fork), " M crates/foo/src/lib.rs\n");
+    }
+
+    #[test]
+    fn namespaced_sandboxes_nest_and_reject_conflicts() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        init_repo(&repo_dir);
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir.clone())).unwrap();
+        let options = || PrepareSandboxOptions {
+            uncommitted_policy: UncommittedPolicy::Include,
+            excludes: Vec::new(),
+            base: None,
+            setup_hooks: Vec::new(),
+            require_cow: false,
+            sparse_paths: Vec::new(),
+            detached: false,
+            command: Vec::new(),
+        };
+
+        for name in ["bug/1234", "bug/99", "alice/refactor"] {
+            let plan = godo.prepare_sandbox(name, options()).unwrap();
+            let _ = plan.session.release().unwrap();
+        }
+        let project_dir = godo.project_dir().unwrap();
+        assert!(project_dir.join("bug/1234/README.md").exists());
+        assert!(git::has_branch(&repo_dir, "godo/bug/1234").unwrap());
+        assert!(godo.read_metadata("bug/1234").unwrap().is_some());
+
+        assert_eq!(
+            godo.all_sandbox_names().unwrap(),
+            vec!["alice/refactor", "bug/1234", "bug/99"]
+        );
+        let names: Vec<String> = godo
+            .list_matching("bug/", false)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.status.name)
+            .collect();
+        assert_eq!(names, vec!["bug/1234", "bug/99"]);
+
+        // Disk usage is only measured on request.
+        assert!(
+            godo.list()
+                .unwrap()
+                .iter()
+                .all(|entry| entry.disk_usage.is_none())
+        );
+        assert!(
+            godo.list_matching("bug/", true)
+                .unwrap()
+                .iter()
+                .all(|entry| entry.disk_usage.is_some())
+        );
+
+        // A namespace is not a sandbox, and cannot become one while it holds sandboxes.
+        assert!(godo.sandbox_status("bug").unwrap().is_none());
+        assert!(godo.prepare_sandbox("bug", options()).is_err());
+        assert!(
+            godo.prepare_sandbox("alice/refactor/more", options())
+                .is_err()
+        );
+
+        for name in ["bug/1234", "bug/99"] {
+            let plan = godo.removal_plan(name).unwrap();
+            godo.remove(&plan, &RemovalOptions::force()).unwrap();
+        }
+        assert!(!project_dir.join("bug").exists());
+        assert!(project_dir.join("alice/refactor").exists());
+    }
+
+    #[test]
+    fn stale_projects_are_listed_and_pruned() {
+        let tmp = tempdir().unwrap();
+        let godo_dir = tmp.path().join("godo");
+        let mut managers = Vec::new();
+        for repo in ["live/app", "gone/app"] {
+            let repo_dir = tmp.path().join(repo);
+            init_repo(&repo_dir);
+            let godo = Godo::new(godo_dir.clone(), Some(repo_dir)).unwrap();
+            godo.prepare_sandbox(
+                "box",
+                PrepareSandboxOptions {
+                    uncommitted_policy: UncommittedPolicy::Include,
+                    excludes: Vec::new(),
+                    base: None,
+                    setup_hooks: Vec::new(),
+                    require_cow: false,
+                    sparse_paths: Vec::new(),
+                    detached: false,
+                    command: Vec::new(),
+                },
+            )
+            .unwrap();
+            managers.push(godo);
+        }
+        fs::remove_dir_all(tmp.path().join("gone")).unwrap();
+
+        let live = &managers[0];
+        let projects = live.projects().unwrap();
+        assert_eq!(projects.len(), 2);
+        let (stale, fresh): (Vec<_>, Vec<_>) =
+            projects.into_iter().partition(|project| project.is_stale);
+        assert_eq!(fresh[0].project_dir, live.project_dir().unwrap());
+        assert_eq!(stale[0].project_dir, managers[1].project_dir().unwrap());
+
+        let other = live.for_project(&fresh[0]).unwrap();
+        assert!(other.sandbox_status("box").unwrap().is_some());
+        assert!(live.prune_project(&fresh[0]).is_err());
+        assert!(live.sandbox_path("box").unwrap().exists());
+
+        live.prune_project(&stale[0]).unwrap();
+        assert!(!stale[0].project_dir.exists());
+        assert_eq!(live.projects().unwrap(), fresh);
+    }
+
+    #[test]
+    fn moved_repositories_and_godo_dirs_are_repaired() {
+        let tmp = tempdir().unwrap();
+        let root = fs::canonicalize(tmp.path()).unwrap();
+        let repo_dir = root.join("old/app");
+        init_repo(&repo_dir);
+        let godo = Godo::new(root.join("godo"), Some(repo_dir)).unwrap();
+        for name in ["box", "bug/1"] {
+            godo.prepare_sandbox(
+                name,
+                PrepareSandboxOptions {
+                    uncommitted_policy: UncommittedPolicy::Include,
+                    excludes: Vec::new(),
+                    base: None,
+                    setup_hooks: Vec::new(),
+                    require_cow: false,
+                    sparse_paths: Vec::new(),
+                    detached: false,
+                    command: Vec::new(),
+                },
+            )
+            .unwrap();
+        }
+        let old_project_dir = godo.project_dir().unwrap();
+
+        assert!(!godo.needs_repair().unwrap());
+
+        // Moving the repository changes its identity; repairing moves the sandboxes over.
+        fs::rename(root.join("old"), root.join("new")).unwrap();
+        let godo = Godo::new(root.join("godo"), Some(root.join("new/app"))).unwrap();
+        assert_ne!(godo.project_dir().unwrap(), old_project_dir);
+        assert!(old_project_dir.exists());
+        assert!(godo.needs_repair().unwrap());
+        godo.repair().unwrap();
+        assert!(!old_project_dir.exists());
+        for name in ["box", "bug/1"] {
+            let status = godo.sandbox_status(name).unwrap().unwrap();
+            assert!(
+                status.is_live() && !status.is_dangling,
+                "{name} should be live"
+            );
+        }
+        let projects = godo.projects().unwrap();
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].repo_dir, root.join("new/app"));
+
+        // Moving the godo directory is noticed from the registry.
+        fs::rename(root.join("godo"), root.join("moved-godo")).unwrap();
+        let godo = Godo::new(root.join("moved-godo"), Some(root.join("new/app"))).unwrap();
+        assert!(godo.needs_repair().unwrap());
+        let report = godo.repair().unwrap();
+        assert!(report.adopted.is_empty());
+        assert_eq!(
+            report.repaired,
+            vec!["box".to_string(), "bug/1".to_string()]
+        );
+        let status = godo.sandbox_status("bug/1").unwrap().unwrap();
+        assert!(status.is_live() && !status.is_dangling);
+        assert!(!godo.needs_repair().unwrap());
+    }
+
+    #[test]
+    fn metadata_records_provenance_and_usage() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        init_repo(&repo_dir);
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir)).unwrap();
+        let options = || PrepareSandboxOptions {
+            uncommitted_policy: UncommittedPolicy::Clean,
+            excludes: vec!["*.log".to_string()],
+            base: None,
+            setup_hooks: Vec::new(),
+            require_cow: false,
+            sparse_paths: Vec::new(),
+            detached: false,
+            command: vec!["cargo".to_string(), "test".to_string()],
+        };
+
+        let plan = godo.prepare_sandbox("box", options()).unwrap();
+        drop(plan);
+        let metadata = godo.metadata("box").unwrap().unwrap();
+        assert_eq!(metadata.command, vec!["cargo", "test"]);
+        assert_eq!(metadata.uncommitted_policy, Some(UncommittedPolicy::Clean));
+        assert_eq!(metadata.excludes, vec!["*.log"]);
+        assert_eq!(metadata.user, current_user());
+        assert!(
+            metadata
+                .last_used_at
+                .is_some_and(|at| at >= metadata.created_at)
+        );
+        assert_eq!(metadata.last_exit_code, None);
+
+        godo.record_exit_code("box", 3).unwrap();
+        godo.prepare_sandbox("box", options()).unwrap();
+        let reused = godo.metadata("box").unwrap().unwrap();
+        assert_eq!(reused.last_exit_code, Some(3));
+        assert!(reused.last_used_at >= metadata.last_used_at);
+        assert_eq!(reused.created_at, metadata.created_at);
+
+        assert!(godo.metadata("missing").unwrap().is_none());
+        assert!(godo.metadata("../escape").is_err());
+    }
+
+    #[test]
+    fn reserved_names_are_unique_and_create_sandboxes() {
+        let tmp = tempdir().unwrap();
+        let repo_dir = tmp.path().join("repo");
+        init_repo(&repo_dir);
+        let godo = Godo::new(tmp.path().join("godo"), Some(repo_dir)).unwrap();
+
+        let reservation = godo.reserve_sandbox_name().unwrap();
+        let name = reservation.name().to_string();
+        assert!(validate_sandbox_name(&name).is_ok());
+        let plan = godo
+            .prepare_reserved_sandbox(
+                reservation,
+                PrepareSandboxOptions {
+                    uncommitted_policy: UncommittedPolicy::Include,
+                    excludes: Vec::new(),
+                    base: None,
+                    setup_hooks: Vec::new(),
+                    require_cow: false,
+                    sparse_paths: Vec::new(),
+                    detached: false,
+                    command: Vec::new(),
+                },
+                &mut |_| {},
+            )
+            .unwrap();
+        assert!(plan.created);
+        assert_eq!(plan.session.name, name);
+        assert!(godo.sandbox_status(&name).unwrap().unwrap().is_live());
+
+        // Held and existing names are both skipped.
+        let held = godo.reserve_sandbox_name().unwrap();
+        let next = godo.reserve_sandbox_name().unwrap();
+        assert_ne!(held.name(), name);
+        assert_ne!(next.name(), name);
+        assert_ne!(next.name(), held.name());
     }
 
     #[test]
@@ -1305,6 +3668,12 @@ mod tests {
                 PrepareSandboxOptions {
                     uncommitted_policy: UncommittedPolicy::Include,
                     excludes: Vec::new(),
+                    base: None,
+                    setup_hooks: Vec::new(),
+                    require_cow: false,
+                    sparse_paths: Vec::new(),
+                    detached: false,
+                    command: Vec::new(),
                 },
             )
             .unwrap();
@@ -1325,6 +3694,12 @@ mod tests {
                 PrepareSandboxOptions {
                     uncommitted_policy: UncommittedPolicy::Include,
                     excludes: Vec::new(),
+                    base: None,
+                    setup_hooks: Vec::new(),
+                    require_cow: false,
+                    sparse_paths: Vec::new(),
+                    detached: false,
+                    command: Vec::new(),
                 },
             )
             .unwrap();
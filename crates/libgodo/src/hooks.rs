@@ -0,0 +1,98 @@
+use std::{
+    io::{BufRead, BufReader, Read},
+    path::Path,
+    process::{Command, Stdio},
+    sync::mpsc,
+    thread,
+};
+
+use crate::error::{GodoError, Result, best_effort};
+
+/// Run a setup hook through the platform shell inside `sandbox_path`.
+///
+/// Stdout and stderr are captured line by line and handed to `on_line` as they arrive. On
+/// failure the combined output is returned inside [`GodoError::HookFailed`].
+pub fn run_setup_hook(
+    sandbox_path: &Path,
+    command: &str,
+    on_line: &mut dyn FnMut(&str),
+) -> Result<()> {
+    let mut child = shell_command(command)
+        .current_dir(sandbox_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| GodoError::HookFailed {
+            command: command.to_string(),
+            status: format!("could not be started: {e}"),
+            output: String::new(),
+        })?;
+
+    let (sender, receiver) = mpsc::channel();
+    let mut readers = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        readers.push(forward_lines(stdout, sender.clone()));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        readers.push(forward_lines(stderr, sender));
+    } else {
+        drop(sender);
+    }
+
+    let mut output = String::new();
+    for line in receiver {
+        on_line(&line);
+        output.push_str(&line);
+        output.push('\n');
+    }
+    for reader in readers {
+        best_effort(reader.join());
+    }
+
+    let status = child.wait()?;
+    if status.success() {
+        return Ok(());
+    }
+
+    let status = match status.code() {
+        Some(code) => format!("exited with status {code}"),
+        None => "terminated by signal".to_string(),
+    };
+    Err(GodoError::HookFailed {
+        command: command.to_string(),
+        status,
+        output,
+    })
+}
+
+/// Build a command that evaluates `command` with the platform shell.
+fn shell_command(command: &str) -> Command {
+    #[cfg(windows)]
+    {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(command);
+        shell
+    }
+    #[cfg(not(windows))]
+    {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(command);
+        shell
+    }
+}
+
+/// Forward each line read from `stream` to `sender` on a background thread.
+fn forward_lines(
+    stream: impl Read + Send + 'static,
+    sender: mpsc::Sender<String>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    })
+}
@@ -7,25 +7,42 @@
 //! sandboxes, query their status, and execute cleanup operations. User-facing
 //! I/O is handled by frontends such as the `godo` CLI.
 
+/// Parallel cloning of source trees into sandboxes.
+mod clone;
 /// Error types for Godo operations.
 mod error;
+/// Gitignore-style exclude matching for sandbox clones.
+mod excludes;
 /// Helper routines for interacting with Git repositories.
 mod git;
 /// High-level orchestration for sandbox lifecycle management.
 mod godo;
+/// Execution of post-create setup hooks.
+mod hooks;
+/// Mapping between sandbox names and branch names.
+mod naming;
+/// Registry of projects with unique identities in the godo directory.
+mod registry;
+/// Undo journal for partially created sandboxes.
+mod rollback;
 /// Lightweight session tracking for concurrent godo runs.
 mod session;
 /// Sandbox metadata persistence helpers.
 mod store;
 /// Domain types for Godo operations.
 mod types;
+/// Disk usage accounting for sandbox directories.
+mod usage;
 
 pub use error::GodoError;
 pub use git::{CommitInfo, DiffStats, MergeStatus};
 pub use godo::Godo;
+pub use naming::BranchNamespace;
 pub use session::{CleanupGuard, ReleaseOutcome};
+pub use store::{with_file_lock, write_atomic};
 pub use types::{
-    CleanupBatch, CleanupFailure, CleanupReport, DiffPlan, PrepareSandboxOptions,
-    PrepareSandboxPlan, RemovalBlocker, RemovalOptions, RemovalOutcome, RemovalPlan,
-    SandboxListEntry, SandboxSession, SandboxStatus, UncommittedPolicy,
+    CleanupBatch, CleanupFailure, CleanupReport, CloneProgress, CloneStats, DiffPlan, DiskUsage,
+    PrepareEvent, PrepareSandboxOptions, PrepareSandboxPlan, ProjectInfo, RemovalBlocker,
+    RemovalOptions, RemovalOutcome, RemovalPlan, RepairReport, SandboxListEntry, SandboxMetadata,
+    SandboxReservation, SandboxSession, SandboxStatus, UncommittedPolicy,
 };
@@ -0,0 +1,249 @@
+use std::{
+    fs,
+    hash::{BuildHasher, RandomState},
+    path::Path,
+};
+
+use crate::error::{GodoError, Result};
+
+/// Branch prefix used for sandbox branches unless overridden.
+const DEFAULT_PREFIX: &str = "godo/";
+
+/// Namespace of the refs that keep sandbox baseline commits alive.
+const BASELINE_REF_PREFIX: &str = "refs/godo/baselines/";
+
+/// Prefix of fully qualified local branch refs.
+const HEADS_PREFIX: &str = "refs/heads/";
+
+/// First words of generated sandbox names.
+const ADJECTIVES: &[&str] = &[
+    "amber", "brisk", "calm", "clever", "crisp", "dusty", "eager", "fuzzy", "gentle", "glossy",
+    "hazy", "humble", "icy", "jolly", "keen", "lively", "lucky", "mellow", "misty", "nimble",
+    "plucky", "quiet", "rapid", "rusty", "shiny", "silent", "snowy", "sunny", "swift", "tidy",
+    "vivid", "witty",
+];
+
+/// Second words of generated sandbox names.
+const NOUNS: &[&str] = &[
+    "badger", "beacon", "canyon", "comet", "cricket", "delta", "ember", "falcon", "fern", "fjord",
+    "glacier", "harbor", "heron", "island", "lantern", "maple", "meadow", "nebula", "otter",
+    "pebble", "pine", "quartz", "raven", "reef", "river", "sparrow", "summit", "thistle", "tundra",
+    "walrus", "willow", "zephyr",
+];
+
+/// Attempts that use a bare word pair before generated names get a numeric suffix.
+const PLAIN_ATTEMPTS: u32 = 16;
+
+/// Generate a readable candidate sandbox name, such as `brisk-otter`, for the `attempt`-th try.
+///
+/// Words are picked at random. Once `attempt` reaches [`PLAIN_ATTEMPTS`], the attempt number is
+/// appended so that a crowded project still yields fresh candidates. Callers are responsible for
+/// checking that the name is free.
+pub fn generate_sandbox_name(attempt: u32) -> String {
+    let random = RandomState::new().hash_one(attempt);
+    let adjective = ADJECTIVES[(random % ADJECTIVES.len() as u64) as usize];
+    let noun = NOUNS[((random >> 32) % NOUNS.len() as u64) as usize];
+    if attempt < PLAIN_ATTEMPTS {
+        format!("{adjective}-{noun}")
+    } else {
+        format!("{adjective}-{noun}-{attempt}")
+    }
+}
+
+/// Mapping between sandbox names and the Git refs that belong to them.
+///
+/// Every sandbox is backed by the branch `<prefix><name>`. Code that renders or parses sandbox
+/// branch names goes through this type, so the prefix is configured in one place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchNamespace {
+    /// Prefix prepended to sandbox names, always ending in `/`.
+    prefix: String,
+}
+
+impl Default for BranchNamespace {
+    fn default() -> Self {
+        Self {
+            prefix: DEFAULT_PREFIX.to_string(),
+        }
+    }
+}
+
+impl BranchNamespace {
+    /// Create a namespace for branches under `prefix`, such as `bots/` or `alice/godo/`.
+    ///
+    /// A trailing `/` is added when missing. Fails when the prefix is empty or would not form
+    /// valid Git branch names.
+    pub fn new(prefix: &str) -> Result<Self> {
+        let prefix = prefix.trim();
+        let prefix = if prefix.ends_with('/') {
+            prefix.to_string()
+        } else {
+            format!("{prefix}/")
+        };
+        validate_prefix(&prefix)?;
+        Ok(Self { prefix })
+    }
+
+    /// The prefix shared by all sandbox branches, ending in `/`.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Return the branch name backing the sandbox `sandbox_name`.
+    pub fn branch(&self, sandbox_name: &str) -> String {
+        format!("{}{sandbox_name}", self.prefix)
+    }
+
+    /// Return the sandbox name a branch belongs to, or `None` for branches outside the
+    /// namespace.
+    ///
+    /// Both short branch names and fully qualified `refs/heads/` refs are accepted.
+    pub fn sandbox_name<'a>(&self, branch: &'a str) -> Option<&'a str> {
+        let branch = branch.strip_prefix(HEADS_PREFIX).unwrap_or(branch);
+        branch
+            .strip_prefix(self.prefix.as_str())
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Return the ref that holds the baseline commit of the sandbox `sandbox_name`.
+    pub(crate) fn baseline_ref(&self, sandbox_name: &str) -> String {
+        format!("{BASELINE_REF_PREFIX}{}", self.branch(sandbox_name))
+    }
+}
+
+/// Whether `name` lies inside the namespace `namespace`, as `bug/1234` lies inside `bug`.
+pub fn is_nested(name: &str, namespace: &str) -> bool {
+    name.strip_prefix(namespace)
+        .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Remove `dir` and then its ancestors, stopping at the first one that is not empty or at
+/// `root`, which is kept.
+///
+/// Hierarchical sandbox names nest directories, and namespace directories such as `bug/` must
+/// not outlive their last sandbox. Removal is best-effort: a directory that cannot be removed
+/// simply ends the walk.
+pub fn prune_empty_dirs(dir: &Path, root: &Path) {
+    let mut current = Some(dir);
+    while let Some(dir) = current {
+        if dir == root || !dir.starts_with(root) || fs::remove_dir(dir).is_err() {
+            break;
+        }
+        current = dir.parent();
+    }
+}
+
+/// Check that `prefix` can start a Git branch name, following `git check-ref-format`.
+fn validate_prefix(prefix: &str) -> Result<()> {
+    let invalid = |reason: &str| {
+        Err(GodoError::OperationError(format!(
+            "Invalid branch prefix '{prefix}': {reason}"
+        )))
+    };
+
+    let body = &prefix[..prefix.len() - 1];
+    if body.is_empty() {
+        return invalid("it must not be empty");
+    }
+    if prefix.starts_with('-') {
+        return invalid("it must not start with '-'");
+    }
+    if prefix.contains("@{") || prefix.contains("..") {
+        return invalid("it must not contain '@{' or '..'");
+    }
+    if let Some(c) = prefix
+        .chars()
+        .find(|c| c.is_ascii_control() || " ~^:?*[\\".contains(*c))
+    {
+        return invalid(&format!("it must not contain {c:?}"));
+    }
+    for component in body.split('/') {
+        if component.is_empty() {
+            return invalid("it must not contain empty path components");
+        }
+        if component.starts_with('.') || component.ends_with(".lock") {
+            return invalid("path components must not start with '.' or end with '.lock'");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn branches_round_trip_through_namespace() {
+        let default = BranchNamespace::default();
+        assert_eq!(default.branch("feature-123"), "godo/feature-123");
+        assert_eq!(
+            default.sandbox_name("godo/feature-123"),
+            Some("feature-123")
+        );
+        assert_eq!(default.sandbox_name("refs/heads/godo/box"), Some("box"));
+        assert_eq!(default.sandbox_name("main"), None);
+        assert_eq!(default.sandbox_name("godo/"), None);
+        assert_eq!(default.baseline_ref("box"), "refs/godo/baselines/godo/box");
+
+        let personal = BranchNamespace::new("alice/godo").unwrap();
+        assert_eq!(personal.prefix(), "alice/godo/");
+        assert_eq!(personal.branch("box"), "alice/godo/box");
+        assert_eq!(personal.sandbox_name("alice/godo/box"), Some("box"));
+        assert_eq!(personal.sandbox_name("godo/box"), None);
+    }
+
+    #[test]
+    fn generated_names_are_word_pairs() {
+        let name = generate_sandbox_name(0);
+        let (adjective, noun) = name.split_once('-').unwrap();
+        assert!(ADJECTIVES.contains(&adjective) && NOUNS.contains(&noun));
+
+        let name = generate_sandbox_name(PLAIN_ATTEMPTS);
+        assert!(name.ends_with(&format!("-{PLAIN_ATTEMPTS}")));
+        assert_eq!(name.matches('-').count(), 2);
+    }
+
+    #[test]
+    fn nesting_follows_namespace_components() {
+        assert!(is_nested("bug/1234", "bug"));
+        assert!(is_nested("alice/refactor/step", "alice"));
+        assert!(!is_nested("bug", "bug"));
+        assert!(!is_nested("bugfix", "bug"));
+    }
+
+    #[test]
+    fn prune_stops_at_non_empty_dirs_and_root() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path().join("project");
+        fs::create_dir_all(root.join("alice/refactor/step")).unwrap();
+        fs::create_dir_all(root.join("alice/other")).unwrap();
+
+        prune_empty_dirs(&root.join("alice/refactor/step"), &root);
+        assert!(!root.join("alice/refactor").exists());
+        assert!(root.join("alice/other").exists());
+
+        prune_empty_dirs(&root.join("alice/other"), &root);
+        assert!(!root.join("alice").exists());
+        assert!(root.exists());
+    }
+
+    #[test]
+    fn invalid_prefixes_are_rejected() {
+        for prefix in [
+            "", "/", "-bots/", "a//b/", "bots..x/", ".hidden/", "x.lock/", "a b/", "a:b/", "a@{b/",
+        ] {
+            assert!(
+                BranchNamespace::new(prefix).is_err(),
+                "Expected '{prefix}' to be rejected"
+            );
+        }
+        for prefix in ["bots", "sandbox/", "alice/godo/", "team.x/bot_1/"] {
+            assert!(
+                BranchNamespace::new(prefix).is_ok(),
+                "Expected '{prefix}' to be accepted"
+            );
+        }
+    }
+}
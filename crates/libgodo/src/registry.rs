@@ -0,0 +1,165 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::store;
+
+/// File name of the project registry in the godo directory.
+pub const REGISTRY_FILE: &str = "projects.toml";
+
+/// File name of the lock serialising registry updates.
+const LOCK_FILE: &str = "projects.lock";
+
+/// FNV-1a offset basis for 64-bit hashes.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// FNV-1a prime for 64-bit hashes.
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// A repository known to godo.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectEntry {
+    /// Canonical path of the repository root.
+    pub repo_dir: PathBuf,
+    /// Project directory the sandboxes were last recorded in, if known.
+    ///
+    /// A mismatch with the directory computed now means the godo directory was moved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_dir: Option<PathBuf>,
+}
+
+impl ProjectEntry {
+    /// Whether the repository has gone away, such as after the checkout was deleted.
+    pub fn is_stale(&self) -> bool {
+        !self.repo_dir.join(".git").exists()
+    }
+}
+
+/// On-disk layout of the registry file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RegistryFile {
+    /// Registered projects keyed by project identity.
+    #[serde(default)]
+    projects: BTreeMap<String, ProjectEntry>,
+}
+
+/// Registry of the projects that keep sandboxes in a godo directory.
+///
+/// Each repository gets its own project directory named by [`project_id`]; the registry maps
+/// those identities back to the repositories they belong to.
+pub struct ProjectRegistry {
+    /// Path of the registry file.
+    path: PathBuf,
+    /// Path of the lock file guarding updates.
+    lock_path: PathBuf,
+}
+
+impl ProjectRegistry {
+    /// Create a registry stored in `godo_dir`.
+    pub fn new(godo_dir: &Path) -> Self {
+        Self {
+            path: godo_dir.join(REGISTRY_FILE),
+            lock_path: godo_dir.join(LOCK_FILE),
+        }
+    }
+
+    /// Read all registered projects, keyed by project identity.
+    pub fn projects(&self) -> Result<BTreeMap<String, ProjectEntry>> {
+        if !self.path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let contents = fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read registry {}", self.path.display()))?;
+        let file: RegistryFile = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse registry {}", self.path.display()))?;
+        Ok(file.projects)
+    }
+
+    /// Apply `change` to the registered projects and save the result.
+    ///
+    /// The registry is locked for the whole read-modify-write cycle, so concurrent updates from
+    /// other godo processes are never lost, and it is replaced atomically so readers never see
+    /// a partial file. Nothing is written when `change` fails.
+    pub fn update<T>(
+        &self,
+        change: impl FnOnce(&mut BTreeMap<String, ProjectEntry>) -> Result<T>,
+    ) -> Result<T> {
+        store::with_file_lock(&self.lock_path, || {
+            let mut projects = self.projects()?;
+            let before = projects.clone();
+            let result = change(&mut projects)?;
+            if projects != before {
+                let encoded = toml::to_string(&RegistryFile { projects })
+                    .context("Failed to encode project registry")?;
+                store::write_atomic(&self.path, encoded.as_bytes())?;
+            }
+            Ok(result)
+        })
+    }
+}
+
+/// Compute the project identity of the repository at `repo_dir`, such as `app-1f2e3d4c`.
+///
+/// The readable `name` is followed by a hash of the canonical repository path, so two
+/// checkouts that share a directory name still get separate project directories. The hash is
+/// FNV-1a, which is stable across Rust versions and platforms.
+pub fn project_id(name: &str, repo_dir: &Path) -> String {
+    let hash = repo_dir
+        .as_os_str()
+        .as_encoded_bytes()
+        .iter()
+        .fold(FNV_OFFSET, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+        });
+    format!("{name}-{:08x}", hash & 0xffff_ffff)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn project_ids_separate_checkouts_with_the_same_name() {
+        let first = project_id("app", Path::new("/home/alice/src/app"));
+        let second = project_id("app", Path::new("/home/alice/work/app"));
+        assert!(first.starts_with("app-") && first.len() == "app-".len() + 8);
+        assert_ne!(first, second);
+        assert_eq!(first, project_id("app", Path::new("/home/alice/src/app")));
+    }
+
+    #[test]
+    fn updates_round_trip_and_failed_updates_write_nothing() {
+        let tmp = tempdir().unwrap();
+        let registry = ProjectRegistry::new(tmp.path());
+        assert!(registry.projects().unwrap().is_empty());
+
+        let entry = ProjectEntry {
+            repo_dir: PathBuf::from("/src/app"),
+            project_dir: Some(PathBuf::from("/godo/app-00000001")),
+        };
+        registry
+            .update(|projects| {
+                projects.insert("app-00000001".to_string(), entry.clone());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(
+            registry.projects().unwrap(),
+            BTreeMap::from([("app-00000001".to_string(), entry)])
+        );
+
+        let result: Result<()> = registry.update(|projects| {
+            projects.clear();
+            anyhow::bail!("refused")
+        });
+        assert!(result.is_err());
+        assert_eq!(registry.projects().unwrap().len(), 1);
+    }
+}
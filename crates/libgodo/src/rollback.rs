@@ -0,0 +1,110 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{error::best_effort, git, naming, store::SandboxMetadataStore};
+
+/// A completed step of sandbox creation that must be undone if creation fails.
+enum Step {
+    /// The sandbox branch was created.
+    Branch(String),
+    /// The sandbox directory was created.
+    Directory(PathBuf),
+    /// The worktree was registered with the source repository.
+    Worktree(PathBuf),
+    /// Metadata was written for the named sandbox.
+    Metadata(String),
+    /// The named ref was created.
+    Ref(String),
+}
+
+/// Journal of the steps taken while creating a sandbox.
+///
+/// Steps are recorded as they are attempted. On failure, [`CreationRollback::roll_back`] undoes
+/// them in reverse order so that no partial sandbox is left behind; on success the journal is
+/// simply dropped.
+pub struct CreationRollback {
+    /// Root of the source repository.
+    repo_dir: PathBuf,
+    /// Godo project directory holding the sandbox.
+    project_dir: PathBuf,
+    /// Metadata store for the project.
+    metadata: SandboxMetadataStore,
+    /// Steps recorded so far, in the order they were taken.
+    steps: Vec<Step>,
+}
+
+impl CreationRollback {
+    /// Start an empty journal for a sandbox of the repository at `repo_dir`, kept in
+    /// `project_dir`.
+    pub fn new(repo_dir: &Path, project_dir: &Path, metadata: SandboxMetadataStore) -> Self {
+        Self {
+            repo_dir: repo_dir.to_path_buf(),
+            project_dir: project_dir.to_path_buf(),
+            metadata,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Record a `git worktree add` that creates `branch` and checks it out at `path`.
+    ///
+    /// Call this before running the command: a failed `worktree add` can still leave the
+    /// branch or directory behind, and undoing a missing step is harmless.
+    pub fn worktree(&mut self, path: &Path, branch: &str) {
+        self.steps.push(Step::Branch(branch.to_string()));
+        self.steps.push(Step::Directory(path.to_path_buf()));
+        self.steps.push(Step::Worktree(path.to_path_buf()));
+    }
+
+    /// Record a `git worktree add --detach` that checks out a detached worktree at `path`.
+    pub fn detached_worktree(&mut self, path: &Path) {
+        self.steps.push(Step::Directory(path.to_path_buf()));
+        self.steps.push(Step::Worktree(path.to_path_buf()));
+    }
+
+    /// Record that metadata is being written for `sandbox`.
+    pub fn metadata(&mut self, sandbox: &str) {
+        self.steps.push(Step::Metadata(sandbox.to_string()));
+    }
+
+    /// Record that the ref `name` is being created.
+    pub fn reference(&mut self, name: &str) {
+        self.steps.push(Step::Ref(name.to_string()));
+    }
+
+    /// Undo every recorded step in reverse order.
+    ///
+    /// Rollback is best-effort: the error that caused it is the one worth reporting, so
+    /// failures here are ignored and the remaining steps are still attempted.
+    pub fn roll_back(mut self) {
+        while let Some(step) = self.steps.pop() {
+            match step {
+                Step::Metadata(sandbox) => {
+                    best_effort(self.metadata.remove(&sandbox));
+                }
+                Step::Ref(name) => {
+                    best_effort(git::delete_ref(&self.repo_dir, &name));
+                }
+                Step::Worktree(path) => {
+                    best_effort(git::remove_worktree(&self.repo_dir, &path, true));
+                }
+                Step::Directory(path) => {
+                    if path.exists() {
+                        best_effort(fs::remove_dir_all(&path));
+                    }
+                    if let Some(parent) = path.parent() {
+                        naming::prune_empty_dirs(parent, &self.project_dir);
+                    }
+                    // Drop any administrative entry left by a half-finished `worktree add`.
+                    best_effort(git::prune_worktrees(&self.repo_dir));
+                }
+                Step::Branch(branch) => {
+                    if git::has_branch(&self.repo_dir, &branch).unwrap_or(false) {
+                        best_effort(git::delete_branch(&self.repo_dir, &branch, true));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -10,11 +10,17 @@ use std::{
 use fs4::FileExt;
 use sysinfo::{Pid, ProcessRefreshKind, RefreshKind, System};
 
-use crate::GodoError;
+use crate::{GodoError, error::best_effort, naming};
 
 /// Directory name used to store sandbox lease files.
 pub const LEASE_DIR_NAME: &str = ".godo-leases";
 
+/// File name, inside the lease directory, of the lock that serialises worktree registration.
+const WORKTREE_LOCK_NAME: &str = "worktrees.lock";
+
+/// File name, inside the lease directory, of the lock that serialises metadata changes.
+const METADATA_LOCK_NAME: &str = "metadata.lock";
+
 /// Track active sessions per sandbox using lightweight lease files.
 #[derive(Clone)]
 pub struct SessionManager {
@@ -50,9 +56,20 @@ pub struct LockedSandbox {
 }
 
 impl Drop for LockedSandbox {
-    #[allow(clippy::let_underscore_must_use)]
     fn drop(&mut self) {
-        let _ = self.lock_file.unlock();
+        best_effort(self.lock_file.unlock());
+    }
+}
+
+/// Project-wide lock, such as the one held while a worktree is registered with a repository.
+pub struct ProjectLock {
+    /// The lock file handle.
+    lock_file: fs::File,
+}
+
+impl Drop for ProjectLock {
+    fn drop(&mut self) {
+        best_effort(self.lock_file.unlock());
     }
 }
 
@@ -70,14 +87,16 @@ pub struct CleanupGuard {
     lock_file: fs::File,
     /// Directory where leases are stored.
     lease_dir: PathBuf,
+    /// Base directory for leases.
+    base_dir: PathBuf,
 }
 
 impl Drop for CleanupGuard {
-    #[allow(clippy::let_underscore_must_use)]
     fn drop(&mut self) {
-        let _ = self.lock_file.unlock();
-        // Best-effort cleanup of the lease directory when no sessions remain.
-        let _ = fs::remove_dir(&self.lease_dir);
+        best_effort(self.lock_file.unlock());
+        // Best-effort cleanup of the lease directory, and any namespace directories holding it,
+        // when no sessions remain.
+        naming::prune_empty_dirs(&self.lease_dir, &self.base_dir);
     }
 }
 
@@ -125,6 +144,57 @@ impl SessionManager {
     /// Acquire an exclusive lock on the sandbox configuration.
     /// This should be held during creation/setup to prevent races.
     pub fn lock(&self, sandbox: &str) -> Result<LockedSandbox, GodoError> {
+        let locked = self.open_lock(sandbox)?;
+        locked.lock_file.lock_exclusive().map_err(map_io)?;
+        Ok(locked)
+    }
+
+    /// Acquire the exclusive lock on the sandbox configuration without waiting.
+    ///
+    /// Returns `None` when another process holds the lock.
+    pub fn try_lock(&self, sandbox: &str) -> Result<Option<LockedSandbox>, GodoError> {
+        let locked = self.open_lock(sandbox)?;
+        match locked.lock_file.try_lock_exclusive() {
+            Ok(()) => Ok(Some(locked)),
+            Err(err) if err.raw_os_error() == fs4::lock_contended_error().raw_os_error() => {
+                Ok(None)
+            }
+            Err(err) => Err(map_io(err)),
+        }
+    }
+
+    /// Acquire the project-wide lock that serialises `git worktree add`.
+    ///
+    /// While adding a worktree, Git reads the administrative directories of the existing ones
+    /// and fails on a directory that another process is still setting up.
+    pub fn lock_worktrees(&self) -> Result<ProjectLock, GodoError> {
+        self.lock_project(WORKTREE_LOCK_NAME)
+    }
+
+    /// Acquire the project-wide lock that serialises changes to sandbox metadata.
+    ///
+    /// Metadata files of all sandboxes share one directory tree, whose directories are created
+    /// and pruned as files come and go, so changes to any of them are serialised.
+    pub fn lock_metadata(&self) -> Result<ProjectLock, GodoError> {
+        self.lock_project(METADATA_LOCK_NAME)
+    }
+
+    /// Acquire the project-wide lock kept in the lease directory file `name`, waiting for it.
+    fn lock_project(&self, name: &str) -> Result<ProjectLock, GodoError> {
+        fs::create_dir_all(&self.base_dir).map_err(map_io)?;
+        let lock_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(self.base_dir.join(name))
+            .map_err(map_io)?;
+        lock_file.lock_exclusive().map_err(map_io)?;
+        Ok(ProjectLock { lock_file })
+    }
+
+    /// Open the lock file for a sandbox, creating its lease directory, without locking it.
+    fn open_lock(&self, sandbox: &str) -> Result<LockedSandbox, GodoError> {
         let lease_dir = self.lease_dir(sandbox);
         fs::create_dir_all(&lease_dir).map_err(map_io)?;
 
@@ -137,8 +207,6 @@ impl SessionManager {
             .open(&lock_path)
             .map_err(map_io)?;
 
-        lock_file.lock_exclusive().map_err(map_io)?;
-
         Ok(LockedSandbox {
             lock_file,
             lease_dir,
@@ -192,10 +260,7 @@ impl SessionLease {
         lock_file.lock_exclusive().map_err(map_io)?;
 
         // Remove our lease first.
-        #[allow(clippy::let_underscore_must_use)]
-        {
-            let _ = fs::remove_file(&self.lease_path);
-        }
+        best_effort(fs::remove_file(&self.lease_path));
 
         prune_stale_leases(&lease_dir)?;
         let remaining = lease_files(&lease_dir)?.len();
@@ -204,6 +269,7 @@ impl SessionLease {
             Ok(ReleaseOutcome::Last(CleanupGuard {
                 lock_file,
                 lease_dir,
+                base_dir: self.base_dir.clone(),
             }))
         } else {
             lock_file.unlock().map_err(map_io)?;
@@ -213,9 +279,8 @@ impl SessionLease {
 }
 
 impl Drop for SessionLease {
-    #[allow(clippy::let_underscore_must_use)]
     fn drop(&mut self) {
-        let _ = fs::remove_file(&self.lease_path);
+        best_effort(fs::remove_file(&self.lease_path));
     }
 }
 
@@ -231,10 +296,7 @@ fn prune_stale_leases(dir: &Path) -> Result<(), GodoError> {
                 continue;
             }
         }
-        #[allow(clippy::let_underscore_must_use)]
-        {
-            let _ = fs::remove_file(lease);
-        }
+        best_effort(fs::remove_file(lease));
     }
 
     Ok(())
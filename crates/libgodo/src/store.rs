@@ -1,16 +1,147 @@
 use std::{
-    fs,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
     path::{Path, PathBuf},
+    process,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
+use fs4::FileExt;
+use toml::{Table, Value};
 
-use crate::types::SandboxMetadata;
+use crate::{
+    error::best_effort,
+    naming,
+    session::{ProjectLock, SessionManager},
+    types::SandboxMetadata,
+};
+
+/// Format version of the metadata files written by this version of godo.
+pub const METADATA_VERSION: u32 = 3;
+
+/// Key holding the format version in metadata files.
+const VERSION_KEY: &str = "version";
+
+/// Forward migrations between metadata formats; entry `i` upgrades version `i + 1` to `i + 2`.
+///
+/// A change to [`SandboxMetadata`] that older files cannot be read into as they are bumps
+/// [`METADATA_VERSION`] and adds a migration here.
+const MIGRATIONS: &[fn(&mut Table)] = &[migrate_v1_to_v2, migrate_v2_to_v3];
+
+/// Upgrade a version 1 file, written before files carried a version.
+///
+/// The oldest of these predate detached sandboxes, so every sandbox they describe has a branch.
+fn migrate_v1_to_v2(table: &mut Table) {
+    table.entry("detached").or_insert(Value::Boolean(false));
+}
+
+/// Upgrade a version 2 file, written before sandboxes recorded their provenance.
+///
+/// The command and excludes are unknown and left empty, like the optional fields added with
+/// them.
+fn migrate_v2_to_v3(table: &mut Table) {
+    for key in ["command", "excludes"] {
+        table.entry(key).or_insert_with(|| Value::Array(Vec::new()));
+    }
+}
+
+/// Decode the contents of a metadata file, upgrading older formats to the current one.
+///
+/// Files from a newer version of godo are refused with an error saying so, rather than being
+/// misread.
+fn decode(contents: &str) -> Result<SandboxMetadata> {
+    let mut table: Table = toml::from_str(contents)?;
+    let version = match table.remove(VERSION_KEY) {
+        None => 1,
+        Some(Value::Integer(version)) => u32::try_from(version)
+            .ok()
+            .filter(|version| *version >= 1)
+            .with_context(|| format!("invalid metadata version {version}"))?,
+        Some(other) => bail!("invalid metadata version {other}"),
+    };
+    if version > METADATA_VERSION {
+        bail!(
+            "metadata format version {version} was written by a newer godo, which supports \
+             up to version {METADATA_VERSION}; upgrade godo to use this sandbox"
+        );
+    }
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        migration(&mut table);
+    }
+    Ok(table.try_into()?)
+}
+
+/// Encode metadata in the current file format.
+fn encode(metadata: &SandboxMetadata) -> Result<String> {
+    let mut table = Table::try_from(metadata)?;
+    table.insert(
+        VERSION_KEY.to_string(),
+        Value::Integer(METADATA_VERSION.into()),
+    );
+    Ok(toml::to_string(&table)?)
+}
+
+/// Replace the file at `path` with `contents` so that readers see the old file or the new one,
+/// never a partial one, even if the process dies midway.
+///
+/// The contents go to a temporary file next to `path`, which is flushed to disk and then
+/// renamed over `path`.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let name = path
+        .file_name()
+        .with_context(|| format!("Invalid file path {}", path.display()))?;
+    let temp = path.with_file_name(format!(".{}.tmp-{}", name.to_string_lossy(), process::id()));
+    if let Err(err) = write_and_rename(&temp, path, contents) {
+        best_effort(fs::remove_file(&temp));
+        return Err(err).with_context(|| format!("Failed to write {}", path.display()));
+    }
+
+    // Flush the directory too, so that the rename itself survives a crash.
+    #[cfg(unix)]
+    if let Some(dir) = path.parent() {
+        File::open(dir)
+            .and_then(|dir| dir.sync_all())
+            .with_context(|| format!("Failed to sync directory {}", dir.display()))?;
+    }
+    Ok(())
+}
+
+/// Run `change` while holding an exclusive lock on the file at `lock_path`, waiting for it.
+///
+/// The lock file is created if needed. Combined with [`write_atomic`], this lets concurrent
+/// processes read, change and rewrite a shared file without losing each other's updates.
+pub fn with_file_lock<T>(lock_path: &Path, change: impl FnOnce() -> Result<T>) -> Result<T> {
+    let lock_file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(lock_path)
+        .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+    lock_file
+        .lock_exclusive()
+        .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+    change()
+}
+
+/// Write `contents` to `temp`, flush it to disk and rename it to `path`.
+fn write_and_rename(temp: &Path, path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = File::create(temp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(temp, path)
+}
 
 /// Store for reading and writing sandbox metadata files.
+///
+/// Files are replaced atomically, so readers never see a partial file. Changes hold the
+/// project's metadata lock, so concurrent godo processes neither lose each other's updates nor
+/// prune a directory that another one is writing into.
 pub struct SandboxMetadataStore {
     /// Directory containing metadata files for sandboxes.
     pub(crate) base_dir: PathBuf,
+    /// Session manager of the project, which provides the metadata lock.
+    sessions: SessionManager,
 }
 
 impl SandboxMetadataStore {
@@ -21,6 +152,7 @@ impl SandboxMetadataStore {
     pub fn new(project_dir: &Path) -> Self {
         Self {
             base_dir: project_dir.join(Self::DIR_NAME),
+            sessions: SessionManager::new(project_dir),
         }
     }
 
@@ -33,35 +165,78 @@ impl SandboxMetadataStore {
 
         let contents = fs::read_to_string(&path)
             .with_context(|| format!("Failed to read metadata file {}", path.display()))?;
-        let metadata = toml::from_str(&contents)
+        let metadata = decode(&contents)
             .with_context(|| format!("Failed to parse metadata file {}", path.display()))?;
         Ok(Some(metadata))
     }
 
     /// Persist metadata for a sandbox, creating the metadata directory if needed.
+    ///
+    /// Metadata for a namespaced sandbox such as `bug/1234` is kept in a matching subdirectory.
     pub fn write(&self, sandbox: &str, metadata: &SandboxMetadata) -> Result<()> {
-        fs::create_dir_all(&self.base_dir).with_context(|| {
-            format!(
-                "Failed to create metadata directory {}",
-                self.base_dir.display()
-            )
-        })?;
+        let lock = self.lock()?;
+        self.write_locked(&lock, sandbox, metadata)
+    }
+
+    /// Acquire the metadata lock, for a series of changes made with the `*_locked` methods.
+    pub fn lock(&self) -> Result<ProjectLock> {
+        Ok(self.sessions.lock_metadata()?)
+    }
+
+    /// Apply `change` to the metadata of a sandbox and persist it, returning the result.
+    ///
+    /// The metadata is read and written under the lock, so concurrent updates are applied one
+    /// after the other. Returns `None`, writing nothing, when the sandbox has no metadata.
+    pub fn update(
+        &self,
+        sandbox: &str,
+        change: impl FnOnce(&mut SandboxMetadata),
+    ) -> Result<Option<SandboxMetadata>> {
+        let lock = self.lock()?;
+        let Some(mut metadata) = self.read(sandbox)? else {
+            return Ok(None);
+        };
+        change(&mut metadata);
+        self.write_locked(&lock, sandbox, &metadata)?;
+        Ok(Some(metadata))
+    }
 
+    /// Persist metadata for a sandbox while the caller holds `_lock`, from [`Self::lock`].
+    pub fn write_locked(
+        &self,
+        _lock: &ProjectLock,
+        sandbox: &str,
+        metadata: &SandboxMetadata,
+    ) -> Result<()> {
         let path = self.metadata_path(sandbox);
-        let encoded = toml::to_string(metadata)
-            .with_context(|| format!("Failed to encode metadata for {sandbox}"))?;
-        fs::write(&path, encoded)
-            .with_context(|| format!("Failed to write metadata file {}", path.display()))?;
-        Ok(())
+        let dir = path.parent().unwrap_or(&self.base_dir);
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create metadata directory {}", dir.display()))?;
+
+        let encoded =
+            encode(metadata).with_context(|| format!("Failed to encode metadata for {sandbox}"))?;
+        write_atomic(&path, encoded.as_bytes())
     }
 
     /// Remove metadata for a sandbox if present.
+    ///
+    /// Directories left empty are pruned under the metadata lock, so a concurrent write cannot
+    /// lose the directory it is writing into.
     pub fn remove(&self, sandbox: &str) -> Result<()> {
+        let lock = self.lock()?;
+        self.remove_locked(&lock, sandbox)
+    }
+
+    /// Remove metadata for a sandbox while the caller holds `_lock`, from [`Self::lock`].
+    pub fn remove_locked(&self, _lock: &ProjectLock, sandbox: &str) -> Result<()> {
         let path = self.metadata_path(sandbox);
         if path.exists() {
             fs::remove_file(&path)
                 .with_context(|| format!("Failed to remove metadata file {}", path.display()))?;
         }
+        if let Some(dir) = path.parent() {
+            naming::prune_empty_dirs(dir, &self.base_dir);
+        }
 
         if self.base_dir.exists() {
             let mut entries = fs::read_dir(&self.base_dir).with_context(|| {
@@ -83,6 +258,31 @@ impl SandboxMetadataStore {
         Ok(())
     }
 
+    /// List the names of all sandboxes with metadata, including namespaced ones.
+    pub fn names(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        let mut pending = vec![self.base_dir.clone()];
+        while let Some(dir) = pending.pop() {
+            if !dir.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(&dir)
+                .with_context(|| format!("Failed to read metadata directory {}", dir.display()))?
+            {
+                let path = entry?.path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else if path.extension().is_some_and(|ext| ext == "toml")
+                    && let Ok(relative) = path.with_extension("").strip_prefix(&self.base_dir)
+                {
+                    names.push(relative.to_string_lossy().replace('\\', "/"));
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
     /// Build the metadata file path for a sandbox name.
     fn metadata_path(&self, sandbox: &str) -> PathBuf {
         self.base_dir.join(format!("{sandbox}.toml"))
@@ -91,6 +291,8 @@ impl SandboxMetadataStore {
 
 #[cfg(test)]
 mod tests {
+    use std::thread;
+
     use tempfile::tempdir;
 
     use super::*;
@@ -104,11 +306,128 @@ mod tests {
             base_commit: "abc123".to_string(),
             base_ref: Some("main".to_string()),
             created_at: 1_700_000_000,
+            baseline_commit: Some("def456".to_string()),
+            detached: false,
+            ..SandboxMetadata::default()
         };
 
         store.write("sandbox", &metadata).unwrap();
         let loaded = store.read("sandbox").unwrap().unwrap();
         assert_eq!(metadata, loaded);
+
+        let contents = fs::read_to_string(store.metadata_path("sandbox")).unwrap();
+        assert!(contents.contains(&format!("version = {METADATA_VERSION}")));
+    }
+
+    #[test]
+    fn unversioned_files_are_upgraded() {
+        let tmp = tempdir().unwrap();
+        let store = SandboxMetadataStore::new(tmp.path());
+        fs::create_dir_all(&store.base_dir).unwrap();
+
+        // Written before versions and detached sandboxes existed.
+        fs::write(
+            store.metadata_path("old"),
+            "base_commit = \"abc123\"\nbase_ref = \"main\"\ncreated_at = 1700000000\n",
+        )
+        .unwrap();
+        // Written before versions, by a build that knew about detached sandboxes.
+        fs::write(
+            store.metadata_path("detached"),
+            "base_commit = \"abc123\"\ncreated_at = 1700000000\ndetached = true\n",
+        )
+        .unwrap();
+
+        let old = store.read("old").unwrap().unwrap();
+        assert_eq!(
+            old,
+            SandboxMetadata {
+                base_commit: "abc123".to_string(),
+                base_ref: Some("main".to_string()),
+                created_at: 1_700_000_000,
+                baseline_commit: None,
+                detached: false,
+                ..SandboxMetadata::default()
+            }
+        );
+        assert!(store.read("detached").unwrap().unwrap().detached);
+
+        // Writing back stores the current version.
+        store.write("old", &old).unwrap();
+        let contents = fs::read_to_string(store.metadata_path("old")).unwrap();
+        assert!(contents.contains(&format!("version = {METADATA_VERSION}")));
+        assert_eq!(store.read("old").unwrap(), Some(old));
+    }
+
+    #[test]
+    fn version_2_files_gain_empty_provenance() {
+        let contents = "version = 2\nbase_commit = \"abc123\"\ncreated_at = 1700000000\n\
+                        detached = true\n";
+        assert_eq!(
+            decode(contents).unwrap(),
+            SandboxMetadata {
+                base_commit: "abc123".to_string(),
+                created_at: 1_700_000_000,
+                detached: true,
+                ..SandboxMetadata::default()
+            }
+        );
+    }
+
+    #[test]
+    fn newer_and_invalid_versions_are_refused() {
+        let newer = format!(
+            "version = {}\nbase_commit = \"abc123\"\ncreated_at = 1\nfuture = [1, 2]\n",
+            METADATA_VERSION + 1
+        );
+        let error = format!("{:#}", decode(&newer).unwrap_err());
+        assert!(error.contains("newer godo"), "unexpected error: {error}");
+
+        for version in ["0", "-1", "\"2\""] {
+            let contents = format!("version = {version}\nbase_commit = \"abc\"\ncreated_at = 1\n");
+            assert!(
+                decode(&contents).is_err(),
+                "version {version} should be refused"
+            );
+        }
+    }
+
+    #[test]
+    fn concurrent_updates_and_removals_are_serialised() {
+        let tmp = tempdir().unwrap();
+        let project_dir = tmp.path().to_path_buf();
+        SandboxMetadataStore::new(&project_dir)
+            .write("ns/counter", &SandboxMetadata::default())
+            .unwrap();
+
+        let workers: Vec<_> = (0..8)
+            .map(|worker| {
+                let project_dir = project_dir.clone();
+                thread::spawn(move || {
+                    // Each thread opens its own store, like a separate godo process would.
+                    let store = SandboxMetadataStore::new(&project_dir);
+                    for _ in 0..25 {
+                        store
+                            .update("ns/counter", |metadata| metadata.created_at += 1)
+                            .unwrap();
+                        // Churn a sibling so the shared `ns` directory is repeatedly pruned.
+                        let sibling = format!("ns/scratch-{worker}");
+                        store.write(&sibling, &SandboxMetadata::default()).unwrap();
+                        store.remove(&sibling).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let store = SandboxMetadataStore::new(&project_dir);
+        let counter = store.read("ns/counter").unwrap().unwrap();
+        assert_eq!(counter.created_at, 8 * 25);
+        assert_eq!(store.names().unwrap(), vec!["ns/counter"]);
+        assert!(store.update("missing", |_| {}).unwrap().is_none());
+        assert!(!store.base_dir.join("missing.toml").exists());
     }
 
     #[test]
@@ -119,6 +438,31 @@ mod tests {
         assert!(store.read("missing").unwrap().is_none());
     }
 
+    #[test]
+    fn namespaced_metadata_nests_and_prunes() {
+        let tmp = tempdir().unwrap();
+        let store = SandboxMetadataStore::new(tmp.path());
+
+        let metadata = SandboxMetadata {
+            base_commit: "abc123".to_string(),
+            base_ref: None,
+            created_at: 1_700_000_002,
+            baseline_commit: None,
+            detached: false,
+            ..SandboxMetadata::default()
+        };
+
+        store.write("bug/1234", &metadata).unwrap();
+        store.write("plain", &metadata).unwrap();
+        assert!(store.base_dir.join("bug/1234.toml").exists());
+        assert_eq!(store.names().unwrap(), vec!["bug/1234", "plain"]);
+        assert_eq!(store.read("bug/1234").unwrap(), Some(metadata));
+
+        store.remove("bug/1234").unwrap();
+        assert!(!store.base_dir.join("bug").exists());
+        assert_eq!(store.names().unwrap(), vec!["plain"]);
+    }
+
     #[test]
     fn remove_metadata_cleans_empty_directory() {
         let tmp = tempdir().unwrap();
@@ -128,6 +472,9 @@ mod tests {
             base_commit: "abc123".to_string(),
             base_ref: None,
             created_at: 1_700_000_001,
+            baseline_commit: None,
+            detached: false,
+            ..SandboxMetadata::default()
         };
 
         store.write("sandbox", &metadata).unwrap();
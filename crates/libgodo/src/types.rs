@@ -1,15 +1,18 @@
-use std::path::PathBuf;
+use std::{ops::AddAssign, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
 
 use crate::{
     error::{GodoError, Result},
     git::{CommitInfo, DiffStats, MergeStatus},
-    session::SessionLease,
+    session::{LockedSandbox, SessionLease},
 };
 
 /// Metadata persisted for a sandbox in the godo project directory.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Besides what godo needs to operate on the sandbox, this records why it exists and how recently
+/// it was used. Fields added after a sandbox was created are empty for it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SandboxMetadata {
     /// Commit hash recorded when the sandbox was created.
     pub base_commit: String,
@@ -17,16 +20,40 @@ pub struct SandboxMetadata {
     pub base_ref: Option<String>,
     /// Unix timestamp (seconds) when the sandbox metadata was created.
     pub created_at: u64,
+    /// Snapshot commit of the uncommitted changes included at creation, if there were any.
+    ///
+    /// Changes made inside the sandbox are measured against it. It moves forward each time
+    /// those changes are committed on their own.
+    pub baseline_commit: Option<String>,
+    /// Whether the sandbox is a detached worktree without a branch of its own.
+    pub detached: bool,
+    /// Command the sandbox was created to run, or inherited from a forked sandbox; empty for an
+    /// interactive shell.
+    pub command: Vec<String>,
+    /// Policy applied to uncommitted changes at creation, if the sandbox was created from the
+    /// working tree.
+    pub uncommitted_policy: Option<UncommittedPolicy>,
+    /// Exclude patterns applied when cloning the sandbox.
+    pub excludes: Vec<String>,
+    /// User who created the sandbox, if known.
+    pub user: Option<String>,
+    /// Host the sandbox was created on, if known.
+    pub host: Option<String>,
+    /// Unix timestamp (seconds) when a session last attached to the sandbox.
+    pub last_used_at: Option<u64>,
+    /// Exit code of the last command run in the sandbox, once one has finished.
+    pub last_exit_code: Option<i32>,
 }
 
 /// Policy for handling uncommitted repository changes when creating a sandbox.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum UncommittedPolicy {
     /// Abort sandbox creation if the repository is dirty.
     Abort,
     /// Include uncommitted changes when creating the sandbox.
     Include,
-    /// Reset the sandbox to a clean state after creation.
+    /// Start the sandbox from a clean `HEAD`, carrying over only ignored files.
     Clean,
 }
 
@@ -35,8 +62,86 @@ pub enum UncommittedPolicy {
 pub struct PrepareSandboxOptions {
     /// Policy for handling uncommitted changes in the source repository.
     pub uncommitted_policy: UncommittedPolicy,
-    /// Directory names to exclude when cloning into the sandbox.
+    /// Gitignore-style glob patterns for paths to exclude when cloning into the sandbox.
     pub excludes: Vec<String>,
+    /// Revision (commit, tag or branch) to create the sandbox from instead of `HEAD`.
+    ///
+    /// When set, tracked files come from a checkout of this revision, only ignored files are
+    /// cloned from the source working tree, and the uncommitted policy does not apply.
+    pub base: Option<String>,
+    /// Shell commands run inside a newly created sandbox before it is handed out.
+    pub setup_hooks: Vec<String>,
+    /// Refuse to create the sandbox when files cannot be reflinked into the godo directory.
+    pub require_cow: bool,
+    /// Directories to restrict the sandbox to with a sparse checkout; empty for a full sandbox.
+    ///
+    /// Only these subtrees, and the files directly inside the directories leading to them,
+    /// are cloned.
+    pub sparse_paths: Vec<String>,
+    /// Create a throwaway sandbox on a detached `HEAD`, without a sandbox branch.
+    ///
+    /// Detached sandboxes cannot be committed. Cleanup removes them whenever they have no
+    /// uncommitted changes, without considering merge status.
+    pub detached: bool,
+    /// Command the sandbox is created to run, recorded in its metadata; empty for a shell.
+    pub command: Vec<String>,
+}
+
+/// Summary of how a new sandbox's files were materialised.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CloneStats {
+    /// Whether copy-on-write clones of source files were confirmed to work in the godo
+    /// directory.
+    pub copy_on_write: bool,
+    /// Files cloned with copy-on-write, sharing storage with the source.
+    pub reflinked_files: u64,
+    /// Files copied byte for byte.
+    pub copied_files: u64,
+    /// Bytes physically written by full copies.
+    pub bytes_written: u64,
+}
+
+impl AddAssign for CloneStats {
+    fn add_assign(&mut self, other: Self) {
+        self.copy_on_write &= other.copy_on_write;
+        self.reflinked_files += other.reflinked_files;
+        self.copied_files += other.copied_files;
+        self.bytes_written += other.bytes_written;
+    }
+}
+
+/// Progress of cloning the source tree into a new sandbox.
+#[derive(Debug, Clone)]
+pub struct CloneProgress {
+    /// Files cloned so far.
+    pub files_done: u64,
+    /// Files found to clone so far; grows until the source tree has been walked.
+    pub total_files: u64,
+    /// Bytes cloned so far.
+    pub bytes_done: u64,
+    /// Bytes found to clone so far; grows until the source tree has been walked.
+    pub total_bytes: u64,
+    /// Repository-relative path of the file most recently cloned.
+    pub path: PathBuf,
+}
+
+/// Progress events reported while preparing a sandbox.
+#[derive(Debug, Clone)]
+pub enum PrepareEvent {
+    /// Cloning the source tree made progress.
+    Clone(CloneProgress),
+    /// A setup hook is about to run.
+    HookStarted {
+        /// The hook command line.
+        command: String,
+    },
+    /// A line of output was produced by a running setup hook.
+    HookOutput {
+        /// The hook command line.
+        command: String,
+        /// The output line, without its trailing newline.
+        line: String,
+    },
 }
 
 /// Result of preparing a sandbox for use.
@@ -46,8 +151,12 @@ pub struct PrepareSandboxPlan {
     pub session: SandboxSession,
     /// Whether the sandbox was created during this call.
     pub created: bool,
-    /// Whether the sandbox was reset to a clean state after creation.
+    /// Whether the sandbox was started from a clean `HEAD`, leaving out uncommitted changes.
     pub cleaned: bool,
+    /// Setup hooks that ran successfully, in order. Empty when the sandbox was reused.
+    pub hooks_run: Vec<String>,
+    /// How the sandbox's files were cloned. All zero when the sandbox was reused.
+    pub clone_stats: CloneStats,
 }
 
 /// Active session lease for a sandbox.
@@ -68,11 +177,31 @@ impl SandboxSession {
     }
 }
 
+/// A generated sandbox name, reserved by [`crate::Godo::reserve_sandbox_name`].
+///
+/// The reservation holds the sandbox's session lock, so no other godo process can claim the
+/// name until the reservation is passed to [`crate::Godo::prepare_reserved_sandbox`] or dropped.
+pub struct SandboxReservation {
+    /// The reserved sandbox name.
+    pub(crate) name: String,
+    /// Session lock held for the name.
+    pub(crate) lock: LockedSandbox,
+}
+
+impl SandboxReservation {
+    /// The reserved sandbox name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
 /// Status information for a sandbox.
 #[derive(Debug, Clone)]
 pub struct SandboxStatus {
     /// The name of the sandbox.
     pub name: String,
+    /// Name of the Git branch backing the sandbox.
+    pub branch: String,
     /// Whether the branch exists.
     pub has_branch: bool,
     /// Whether the worktree exists.
@@ -95,12 +224,15 @@ pub struct SandboxStatus {
     pub unmerged_commits: Vec<CommitInfo>,
     /// Whether the worktree is dangling (no backing directory).
     pub is_dangling: bool,
+    /// Whether the sandbox was created detached, without a branch of its own.
+    pub detached: bool,
 }
 
 impl SandboxStatus {
-    /// Returns true if the sandbox has both a worktree and a branch.
+    /// Returns true if the sandbox has both a worktree and a branch, or is a detached sandbox
+    /// with a worktree.
     pub fn is_live(&self) -> bool {
-        self.has_branch
+        (self.has_branch || self.detached)
             && self.has_worktree
             && self.has_worktree_dir
             && (self.worktree_detached || self.worktree_branch_matches)
@@ -110,6 +242,8 @@ impl SandboxStatus {
     pub fn component_status(&self) -> String {
         let branch = if self.has_branch {
             "present"
+        } else if self.detached {
+            "none (detached sandbox)"
         } else {
             "missing"
         };
@@ -148,6 +282,18 @@ impl SandboxStatus {
     }
 }
 
+/// Disk space used by a sandbox directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiskUsage {
+    /// Sum of the sizes of all files in the sandbox.
+    pub apparent_bytes: u64,
+    /// Bytes in extents owned by the sandbox alone, when the filesystem can report them.
+    pub exclusive_bytes: Option<u64>,
+    /// Bytes in extents shared through reflinks with the source or other sandboxes, when the
+    /// filesystem can report them.
+    pub shared_bytes: Option<u64>,
+}
+
 /// List entry combining sandbox status with active connection count.
 #[derive(Debug, Clone)]
 pub struct SandboxListEntry {
@@ -155,6 +301,32 @@ pub struct SandboxListEntry {
     pub status: SandboxStatus,
     /// Number of active godo sessions in the sandbox.
     pub active_connections: usize,
+    /// Disk space used by the sandbox directory, if it exists and usage was measured.
+    pub disk_usage: Option<DiskUsage>,
+}
+
+/// A project registered in the godo directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectInfo {
+    /// Project identity, which is also the name of the project directory.
+    pub id: String,
+    /// Root of the source repository the project's sandboxes belong to.
+    pub repo_dir: PathBuf,
+    /// Directory holding the project's sandboxes.
+    pub project_dir: PathBuf,
+    /// Whether the source repository no longer exists, leaving the sandboxes orphaned.
+    pub is_stale: bool,
+}
+
+/// Outcome of repairing a project's sandboxes with [`crate::Godo::repair`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    /// Identities of previous project directories whose sandboxes were moved over.
+    pub previous_projects: Vec<String>,
+    /// Sandboxes moved over from previous project directories.
+    pub adopted: Vec<String>,
+    /// Sandboxes whose worktree links were repaired.
+    pub repaired: Vec<String>,
 }
 
 /// Plan describing how to show a diff for a sandbox.
@@ -172,6 +344,10 @@ pub struct DiffPlan {
     pub fallback_target: Option<String>,
     /// Untracked files to diff with `git diff --no-index`.
     pub untracked_files: Vec<PathBuf>,
+    /// Snapshot tree of the sandbox to diff against the base instead of the working tree.
+    ///
+    /// Set when diffing against a baseline: the snapshot already covers untracked files.
+    pub target_tree: Option<String>,
 }
 
 /// Reasons that block a sandbox removal.